@@ -1,12 +1,21 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use rand::seq::SliceRandom;
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, layout::Position, Terminal};
 use tokio::sync::mpsc;
 
-use crate::config::{load_config, reset_config, save_config};
+use crate::clipboard::copy_to_clipboard;
+use crate::commands::check_for_update;
+use crate::config::{
+    config_permission_warnings, create_profile, get_config_value, load_config, load_project_config,
+    next_profile, reset_config, save_config, set_config_value_unchecked, switch_profile, use_profile_for_run,
+    validate_config_value, ProjectConfig,
+};
 
 const ANALYZING_SYNONYMS: &[&str] = &[
     "Scrutinizing",
@@ -20,13 +29,34 @@ const ANALYZING_SYNONYMS: &[&str] = &[
     "Elucidating",
     "Interrogating",
 ];
-use crate::providers::create_provider;
+use crate::providers::{create_provider, create_provider_for};
+use crate::secrets::key_fields;
 use crate::types::{AnalysisResult, AppState, Command, Config, ProviderType, Screen, SettingsField};
-use crate::ui::{MainScreen, SettingsScreen};
+use crate::ui::theme::score_label;
+use crate::ui::{MainScreen, MainScreenState, ModelPicker, SettingsScreen, SettingsScreenState};
+
+/// How long Ctrl+R shows an API key being edited
+const REVEAL_DURATION: Duration = Duration::from_secs(3);
+
+/// Lines scrolled per mouse wheel step
+const WHEEL_SCROLL_LINES: i32 = 3;
+
+/// Maximum number of undo steps kept for the prompt
+const UNDO_LIMIT: usize = 200;
+
+/// Kind of prompt edit, used to group consecutive edits into one undo step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+}
 
 /// Message from async analysis task
 enum AsyncMessage {
     AnalysisComplete(Result<AnalysisResult>),
+    ConnectionTested(ProviderType, Result<Duration>),
+    ModelsListed(ProviderType, Result<Vec<String>>),
+    UpdateAvailable(String),
 }
 
 /// Main application structure
@@ -39,18 +69,38 @@ pub struct App {
     prompt: String,
     /// Cursor position in prompt
     cursor_position: usize,
+    /// Prompt snapshots (text, cursor) for undo
+    undo_stack: Vec<(String, usize)>,
+    /// Prompt snapshots (text, cursor) for redo
+    redo_stack: Vec<(String, usize)>,
+    /// Kind of the most recent prompt edit
+    last_edit: Option<EditKind>,
     /// Analysis result
     result: Option<AnalysisResult>,
+    /// Results scroll position and layout from the last render
+    main_state: MainScreenState,
+    /// Prompt the current result was produced for
+    analyzed_prompt: Option<String>,
     /// Error message
     error: Option<String>,
+    /// Informational message, such as a copy confirmation
+    notice: Option<String>,
+    /// Notice is a warning
+    notice_is_warning: bool,
     /// Should quit
     should_quit: bool,
     /// Configuration
     config: Config,
+    /// Overrides from the project's `.mahoraga.toml`, if any
+    project: Option<ProjectConfig>,
+    /// Configuration with project overrides applied, used for analysis
+    active_config: Config,
     /// Command menu selected index
     command_selected: usize,
     /// Command menu filter
     command_filter: String,
+    /// Provider whose settings tab is shown
+    settings_tab: ProviderType,
     /// Settings screen selected field
     settings_selected: usize,
     /// Settings editing mode
@@ -59,12 +109,26 @@ pub struct App {
     settings_edit_value: String,
     /// Settings edit cursor position
     settings_cursor: usize,
+    /// When the API key being edited stops being shown in full
+    settings_reveal_until: Option<Instant>,
     /// Settings message
     settings_message: Option<String>,
     /// Settings message is error
     settings_is_error: bool,
     /// Working copy of config for settings
     settings_config: Config,
+    /// A connection test is in flight
+    settings_testing: bool,
+    /// Open model dropdown on the settings screen
+    model_picker: Option<ModelPicker>,
+    /// Models listed by each provider this session
+    model_cache: HashMap<ProviderType, Vec<String>>,
+    /// Provider whose models are being fetched
+    models_loading: Option<ProviderType>,
+    /// Settings popup layout from the last render
+    settings_state: SettingsScreenState,
+    /// Newer release found by the startup check
+    update_available: Option<String>,
     /// Animation frame for analyzing state (0, 1, 2 = 1, 2, 3 dots)
     analyzing_animation_frame: u8,
     /// Selected synonym for analyzing message
@@ -74,28 +138,66 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let config = load_config()?;
+    pub fn new(profile: Option<&str>) -> Result<Self> {
+        let mut config = load_config()?;
+        if let Some(name) = profile {
+            use_profile_for_run(&mut config, name)?;
+        }
+        let project = load_project_config()?;
+        Self::with_config(config, project)
+    }
+
+    fn with_config(config: Config, project: Option<ProjectConfig>) -> Result<Self> {
+        let active_config = match &project {
+            Some(project) => project.apply(&config)?,
+            None => config.clone(),
+        };
         let settings_config = config.clone();
+        let warnings = config_permission_warnings();
+        let notice_is_warning = !warnings.is_empty();
+        let notice = if notice_is_warning {
+            Some(format!("{} (Esc to dismiss)", warnings.join(" | ")))
+        } else {
+            project
+                .as_ref()
+                .map(|project| format!("Using project settings from {}", project.path.display()))
+        };
 
         Ok(Self {
             screen: Screen::Main,
             state: AppState::Idle,
             prompt: String::new(),
             cursor_position: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit: None,
             result: None,
+            main_state: MainScreenState::default(),
+            analyzed_prompt: None,
             error: None,
+            notice,
+            notice_is_warning,
             should_quit: false,
             config,
+            project,
+            active_config,
             command_selected: 0,
             command_filter: String::new(),
+            settings_tab: ProviderType::default(),
             settings_selected: 0,
             settings_editing: false,
             settings_edit_value: String::new(),
             settings_cursor: 0,
+            settings_reveal_until: None,
             settings_message: None,
             settings_is_error: false,
             settings_config,
+            settings_testing: false,
+            model_picker: None,
+            model_cache: HashMap::new(),
+            models_loading: None,
+            settings_state: SettingsScreenState::default(),
+            update_available: None,
             analyzing_animation_frame: 0,
             analyzing_word: String::new(),
             animation_tick_counter: 0,
@@ -106,6 +208,14 @@ impl App {
     pub async fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<()> {
         let (tx, mut rx) = mpsc::channel::<AsyncMessage>(1);
 
+        // Failures are ignored: a missed notice is not worth interrupting for
+        let update_tx = tx.clone();
+        tokio::spawn(async move {
+            if let Ok(Some(version)) = check_for_update().await {
+                let _ = update_tx.send(AsyncMessage::UpdateAvailable(version)).await;
+            }
+        });
+
         loop {
             // Draw UI
             terminal.draw(|f| self.render(f))?;
@@ -118,6 +228,7 @@ impl App {
                         match result {
                             Ok(analysis) => {
                                 self.result = Some(analysis);
+                                self.main_state.results_scroll = 0;
                                 self.state = AppState::ShowingResults;
                                 self.error = None;
                             }
@@ -126,6 +237,43 @@ impl App {
                             }
                         }
                     }
+                    AsyncMessage::ConnectionTested(provider, result) => {
+                        self.settings_testing = false;
+                        match result {
+                            Ok(latency) => {
+                                self.settings_message = Some(format!(
+                                    "Connected to {} in {} ms",
+                                    provider,
+                                    latency.as_millis()
+                                ));
+                                self.settings_is_error = false;
+                            }
+                            Err(e) => {
+                                self.settings_message = Some(format!("Error: {:#}", e));
+                                self.settings_is_error = true;
+                            }
+                        }
+                    }
+                    AsyncMessage::UpdateAvailable(version) => {
+                        self.update_available = Some(version);
+                    }
+                    // Dropped if the key or URL changed while it was loading
+                    AsyncMessage::ModelsListed(provider, result) if self.models_loading == Some(provider) => {
+                        self.models_loading = None;
+                        match result {
+                            Ok(models) => {
+                                self.model_cache.insert(provider, models);
+                            }
+                            Err(e) => {
+                                // Not cached, so the list is fetched again next
+                                // time. The picker stays open so a name can
+                                // still be typed.
+                                self.settings_message = Some(format!("Error: {:#}", e));
+                                self.settings_is_error = true;
+                            }
+                        }
+                    }
+                    AsyncMessage::ModelsListed(..) => {}
                 }
             }
 
@@ -140,16 +288,21 @@ impl App {
 
             // Handle input events
             if event::poll(Duration::from_millis(50))? {
-                if let Event::Key(key) = event::read()? {
-                    // Handle Ctrl+C globally
-                    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
-                        self.should_quit = true;
-                    } else {
-                        match self.screen {
-                            Screen::Main => self.handle_main_input(key, &tx).await?,
-                            Screen::Settings => self.handle_settings_input(key.code)?,
+                match event::read()? {
+                    Event::Key(key) => {
+                        // Handle Ctrl+C globally
+                        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                            self.should_quit = true;
+                        } else {
+                            match self.screen {
+                                Screen::Main => self.handle_main_input(key, &tx).await?,
+                                Screen::Settings => self.handle_settings_input(key, &tx)?,
+                            }
                         }
                     }
+                    Event::Paste(text) => self.handle_paste(&text),
+                    Event::Mouse(mouse) => self.handle_mouse(mouse, &tx),
+                    _ => {}
                 }
             }
 
@@ -161,18 +314,22 @@ impl App {
         Ok(())
     }
 
-    fn render(&self, frame: &mut ratatui::Frame) {
+    fn render(&mut self, frame: &mut ratatui::Frame) {
         match self.screen {
             Screen::Main => {
                 let screen = MainScreen::new(&self.prompt, self.cursor_position)
                     .result(self.result.as_ref())
                     .state(self.state)
                     .error(self.error.as_deref())
+                    .notice(self.notice.as_deref(), self.notice_is_warning)
                     .command_menu(self.command_selected, self.command_filter.clone())
-                    .provider(self.config.provider.active)
+                    .provider(self.active_config.provider.active)
+                    .profile(&self.active_config.active_profile)
+                    .min_score(self.active_config.analysis.min_score)
+                    .update_available(self.update_available.as_deref())
                     .analyzing_animation(&self.analyzing_word, self.analyzing_animation_frame);
 
-                frame.render_widget(screen, frame.area());
+                frame.render_stateful_widget(screen, frame.area(), &mut self.main_state);
             }
             Screen::Settings => {
                 // Render main screen as frozen background
@@ -180,23 +337,45 @@ impl App {
                     .result(self.result.as_ref())
                     .state(self.state)
                     .error(self.error.as_deref())
+                    .notice(self.notice.as_deref(), self.notice_is_warning)
                     .command_menu(self.command_selected, self.command_filter.clone())
-                    .provider(self.config.provider.active)
+                    .provider(self.active_config.provider.active)
+                    .profile(&self.active_config.active_profile)
+                    .min_score(self.active_config.analysis.min_score)
+                    .update_available(self.update_available.as_deref())
                     .analyzing_animation(&self.analyzing_word, self.analyzing_animation_frame);
-                frame.render_widget(bg, frame.area());
+                frame.render_stateful_widget(bg, frame.area(), &mut self.main_state);
 
                 // Overlay settings popup on top
+                let picker_models = SettingsField::fields_for_provider(self.settings_tab)
+                    .get(self.settings_selected)
+                    .and_then(SettingsField::model_provider)
+                    .and_then(|provider| match self.model_cache.get(&provider) {
+                        Some(models) => Some(models.as_slice()),
+                        // Nothing to list after a failed fetch
+                        None if self.models_loading != Some(provider) => Some(&[][..]),
+                        None => None,
+                    });
                 let screen = SettingsScreen::new(&self.settings_config)
+                    .tab(self.settings_tab)
                     .selected(self.settings_selected)
                     .editing(self.settings_editing, &self.settings_edit_value, self.settings_cursor)
-                    .message(self.settings_message.as_deref(), self.settings_is_error);
+                    .reveal(self.settings_reveal_until.is_some_and(|until| Instant::now() < until))
+                    .message(self.settings_message.as_deref(), self.settings_is_error)
+                    .model_picker(self.model_picker.as_ref(), picker_models)
+                    .project(self.project.as_ref());
 
-                frame.render_widget(screen, frame.area());
+                frame.render_stateful_widget(screen, frame.area(), &mut self.settings_state);
             }
         }
     }
 
     async fn handle_main_input(&mut self, key: KeyEvent, tx: &mpsc::Sender<AsyncMessage>) -> Result<()> {
+        // Warnings stay up until dismissed; other notices clear on any key
+        if !self.notice_is_warning || key.code == KeyCode::Esc {
+            self.notice = None;
+            self.notice_is_warning = false;
+        }
         match self.state {
             AppState::Analyzing => {
                 // Can only cancel with Escape
@@ -221,151 +400,388 @@ impl App {
             }
             KeyCode::Enter => {
                 if self.prompt.starts_with('/') {
-                    // Try to execute as command
-                    let cmd = if self.state == AppState::CommandMenu {
-                        // Use selected command from menu
-                        self.filtered_commands().get(self.command_selected).copied()
-                    } else {
-                        // Find exact match
-                        Command::all().iter().find(|c| c.name() == self.prompt).copied()
-                    };
-
-                    if let Some(cmd) = cmd {
-                        self.execute_command(cmd);
-                        self.prompt.clear();
-                        self.cursor_position = 0;
-                    }
-                    self.state = AppState::Idle;
-                    self.command_filter.clear();
+                    self.submit_command();
                 } else if !self.prompt.is_empty() && self.state != AppState::Analyzing {
                     // Not a command, analyze
                     self.start_analysis(tx.clone()).await;
                 }
             }
-            KeyCode::Char(c) => {
+            KeyCode::Char(c) if is_ctrl(&key) => match c {
                 // Ctrl+U: delete from cursor to start of line
-                if c == 'u' && key.modifiers.contains(KeyModifiers::CONTROL) {
-                    if self.cursor_position > 0 {
-                        let before_cursor = &self.prompt[..self.cursor_position];
-                        let line_start = before_cursor.rfind('\n').map(|i| i + 1).unwrap_or(0);
-                        self.prompt.drain(line_start..self.cursor_position);
-                        self.cursor_position = line_start;
-                        self.error = None;
-
-                        // Update command menu state
-                        if self.prompt.starts_with('/') {
-                            self.command_filter = self.prompt.clone();
-                            self.command_selected = 0;
-                            if self.filtered_commands().is_empty() {
-                                self.state = AppState::Idle;
-                                self.command_filter.clear();
-                            } else {
-                                self.state = AppState::CommandMenu;
-                            }
-                        } else {
-                            self.state = AppState::Idle;
-                            self.command_filter.clear();
-                        }
-                    }
-                    return Ok(());
+                'u' => {
+                    let line_start = line_start(&self.prompt, self.cursor_position);
+                    self.delete_prompt_range(line_start, self.cursor_position);
                 }
-
+                'w' => {
+                    let word_start = prev_word_start(&self.prompt, self.cursor_position);
+                    self.delete_prompt_range(word_start, self.cursor_position);
+                }
+                'z' => self.undo_prompt_edit(),
+                'y' => self.redo_prompt_edit(),
+                _ => {}
+            },
+            // Alt+B / Alt+F: word motions as sent by most macOS terminals
+            KeyCode::Char('b') if is_alt(&key) => {
+                self.move_prompt_cursor(prev_word_start(&self.prompt, self.cursor_position));
+            }
+            KeyCode::Char('f') if is_alt(&key) => {
+                self.move_prompt_cursor(next_word_end(&self.prompt, self.cursor_position));
+            }
+            KeyCode::Char(c) => {
+                self.record_prompt_edit(Some(EditKind::Insert));
                 self.prompt.insert(self.cursor_position, c);
-                self.cursor_position += 1;
+                self.cursor_position += c.len_utf8();
                 self.error = None;
+                self.update_command_menu();
 
-                // Show command menu when prompt starts with /
-                if self.prompt.starts_with('/') {
-                    self.command_filter = self.prompt.clone();
-                    self.command_selected = 0;
-                    // Hide if no matching commands
-                    if self.filtered_commands().is_empty() {
-                        self.state = AppState::Idle;
-                        self.command_filter.clear();
-                    } else {
-                        self.state = AppState::CommandMenu;
-                    }
-                } else if self.state == AppState::CommandMenu {
-                    self.state = AppState::Idle;
-                    self.command_filter.clear();
+                // Start a new undo step after each word
+                if c.is_whitespace() {
+                    self.last_edit = None;
                 }
             }
-            KeyCode::Tab => {
+            KeyCode::Tab if self.state == AppState::CommandMenu => {
                 // Insert selected command and hide menu
-                if self.state == AppState::CommandMenu {
-                    let commands = self.filtered_commands();
-                    if let Some(cmd) = commands.get(self.command_selected) {
-                        self.prompt = cmd.name().to_string();
-                        self.cursor_position = self.prompt.len();
-                    }
-                    self.state = AppState::Idle;
-                    self.command_filter.clear();
+                let commands = self.filtered_commands();
+                if let Some(cmd) = commands.get(self.command_selected) {
+                    self.record_prompt_edit(None);
+                    self.prompt = cmd.name().to_string();
+                    self.cursor_position = self.prompt.len();
                 }
+                self.state = AppState::Idle;
+                self.command_filter.clear();
             }
-            KeyCode::Backspace => {
-                if self.cursor_position > 0 {
-                    // Normal backspace: delete one character
-                    self.cursor_position -= 1;
-                    self.prompt.remove(self.cursor_position);
-                    self.error = None;
-
-                    // Update command menu state
-                    if self.prompt.starts_with('/') {
-                        self.command_filter = self.prompt.clone();
-                        self.command_selected = 0;
-                        // Hide if no matching commands
-                        if self.filtered_commands().is_empty() {
-                            self.state = AppState::Idle;
-                            self.command_filter.clear();
-                        } else {
-                            self.state = AppState::CommandMenu;
-                        }
-                    } else {
-                        self.state = AppState::Idle;
-                        self.command_filter.clear();
-                    }
-                }
+            KeyCode::Backspace
+                if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                let word_start = prev_word_start(&self.prompt, self.cursor_position);
+                self.delete_prompt_range(word_start, self.cursor_position);
             }
-            KeyCode::Delete => {
-                if self.cursor_position < self.prompt.len() {
-                    self.prompt.remove(self.cursor_position);
-                    self.error = None;
-                }
+            KeyCode::Backspace if self.cursor_position > 0 => {
+                // Normal backspace: delete one character
+                self.record_prompt_edit(Some(EditKind::Delete));
+                self.cursor_position = prev_char_boundary(&self.prompt, self.cursor_position);
+                self.prompt.remove(self.cursor_position);
+                self.error = None;
+                self.prompt_deleted();
+            }
+            KeyCode::Delete if self.cursor_position < self.prompt.len() => {
+                self.record_prompt_edit(Some(EditKind::Delete));
+                self.prompt.remove(self.cursor_position);
+                self.error = None;
+            }
+            KeyCode::Left if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.move_prompt_cursor(prev_word_start(&self.prompt, self.cursor_position));
+            }
+            KeyCode::Right if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.move_prompt_cursor(next_word_end(&self.prompt, self.cursor_position));
             }
             KeyCode::Left => {
-                if self.cursor_position > 0 {
-                    self.cursor_position -= 1;
-                }
+                self.move_prompt_cursor(prev_char_boundary(&self.prompt, self.cursor_position));
             }
             KeyCode::Right => {
-                if self.cursor_position < self.prompt.len() {
-                    self.cursor_position += 1;
+                self.move_prompt_cursor(next_char_boundary(&self.prompt, self.cursor_position));
+            }
+            KeyCode::Up if self.state == AppState::CommandMenu => {
+                self.command_selected = self.command_selected.saturating_sub(1);
+            }
+            KeyCode::Down if self.state == AppState::CommandMenu => {
+                let commands = self.filtered_commands();
+                if self.command_selected < commands.len().saturating_sub(1) {
+                    self.command_selected += 1;
                 }
             }
             KeyCode::Up => {
-                if self.state == AppState::CommandMenu && self.command_selected > 0 {
-                    self.command_selected -= 1;
-                }
+                // Move to the previous line, or to the start on the first line
+                let position = line_up(&self.prompt, self.cursor_position).unwrap_or(0);
+                self.move_prompt_cursor(position);
             }
             KeyCode::Down => {
-                if self.state == AppState::CommandMenu {
-                    let commands = self.filtered_commands();
-                    if self.command_selected < commands.len().saturating_sub(1) {
-                        self.command_selected += 1;
-                    }
-                }
+                // Move to the next line, or to the end on the last line
+                let position =
+                    line_down(&self.prompt, self.cursor_position).unwrap_or(self.prompt.len());
+                self.move_prompt_cursor(position);
+            }
+            KeyCode::PageUp => {
+                let page = self.main_state.results_page();
+                self.main_state.scroll_results(-page);
+            }
+            KeyCode::PageDown => {
+                let page = self.main_state.results_page();
+                self.main_state.scroll_results(page);
             }
             KeyCode::Home => {
-                self.cursor_position = 0;
+                self.move_prompt_cursor(0);
             }
             KeyCode::End => {
-                self.cursor_position = self.prompt.len();
+                self.move_prompt_cursor(self.prompt.len());
             }
             _ => {}
         }
         Ok(())
     }
 
+    /// Execute the command typed in the prompt or selected in the menu
+    fn submit_command(&mut self) {
+        // Try to execute as command
+        let (name, arg) = self
+            .prompt
+            .split_once(char::is_whitespace)
+            .map(|(name, arg)| (name, arg.trim()))
+            .unwrap_or((self.prompt.as_str(), ""));
+
+        let cmd = if self.state == AppState::CommandMenu {
+            // Use selected command from menu
+            self.filtered_commands().get(self.command_selected).copied()
+        } else {
+            // Find exact match
+            Command::all().iter().find(|c| c.name() == name).copied()
+        };
+
+        if let Some(cmd) = cmd {
+            let arg = arg.to_string();
+            self.execute_command(cmd, &arg);
+            self.prompt.clear();
+            self.cursor_position = 0;
+        }
+        self.state = AppState::Idle;
+        self.command_filter.clear();
+    }
+
+    /// Insert pasted text at the cursor as a single edit
+    fn handle_paste(&mut self, text: &str) {
+        if self.screen == Screen::Settings {
+            self.handle_settings_paste(text);
+            return;
+        }
+        if self.state == AppState::Analyzing {
+            return;
+        }
+
+        // Terminals may send CR or CRLF line endings inside a paste
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if text.is_empty() {
+            return;
+        }
+
+        self.record_prompt_edit(None);
+        self.prompt.insert_str(self.cursor_position, &text);
+        self.cursor_position += text.len();
+        self.error = None;
+        self.update_command_menu();
+    }
+
+    /// Paste into the field being edited, or start editing the selected field
+    /// with the pasted text, e.g. an API key copied from a dashboard
+    fn handle_settings_paste(&mut self, text: &str) {
+        // Settings values are single-line, and copied keys often end in a newline
+        let text = text.replace(['\r', '\n'], "");
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+
+        if let Some(picker) = self.model_picker.as_mut() {
+            picker.filter.push_str(text);
+            picker.selected = 0;
+            return;
+        }
+
+        if !self.settings_editing {
+            let fields = SettingsField::fields_for_provider(self.settings_tab);
+            let Some(field) = fields.get(self.settings_selected) else {
+                return;
+            };
+            if field.is_button() || field.is_provider_selector() || field.model_provider().is_some() {
+                return;
+            }
+            self.settings_editing = true;
+            self.settings_edit_value.clear();
+            self.settings_cursor = 0;
+        }
+
+        self.settings_edit_value.insert_str(self.settings_cursor, text);
+        self.settings_cursor += text.len();
+        self.settings_message = None;
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, tx: &mpsc::Sender<AsyncMessage>) {
+        match (self.screen, mouse.kind) {
+            (Screen::Main, MouseEventKind::ScrollUp) => {
+                self.main_state.scroll_results(-WHEEL_SCROLL_LINES);
+            }
+            (Screen::Main, MouseEventKind::ScrollDown) => {
+                self.main_state.scroll_results(WHEEL_SCROLL_LINES);
+            }
+            (Screen::Main, MouseEventKind::Down(MouseButton::Left)) => {
+                self.handle_main_click(mouse.column, mouse.row);
+            }
+            (Screen::Settings, MouseEventKind::Down(MouseButton::Left)) => {
+                self.handle_settings_click(mouse.column, mouse.row, tx);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_main_click(&mut self, column: u16, row: u16) {
+        if self.state == AppState::Analyzing {
+            return;
+        }
+        let position = Position::new(column, row);
+
+        // Clicking a command menu entry runs it
+        if let Some(menu_area) = self.main_state.command_menu_area {
+            if menu_area.contains(position) {
+                let index = (row - menu_area.y) as usize;
+                if index < self.filtered_commands().len() {
+                    self.command_selected = index;
+                    self.submit_command();
+                }
+                return;
+            }
+        }
+
+        // Clicking in the prompt places the cursor
+        let prompt_area = self.main_state.prompt_area;
+        if prompt_area.contains(position) {
+            let line = (row - prompt_area.y + self.main_state.prompt_scroll) as usize;
+            let column = (column - prompt_area.x) as usize;
+            self.move_prompt_cursor(position_at(&self.prompt, line, column));
+        }
+    }
+
+    fn handle_settings_click(&mut self, column: u16, row: u16, tx: &mpsc::Sender<AsyncMessage>) {
+        // Clicking anywhere closes the model dropdown
+        if self.model_picker.take().is_some() {
+            return;
+        }
+        if let Some(tab) = self.settings_state.tab_at(column, row) {
+            if self.settings_editing {
+                let fields = SettingsField::fields_for_provider(self.settings_tab);
+                self.commit_settings_edit(&fields);
+            }
+            if !self.settings_editing {
+                self.switch_settings_tab(tab);
+            }
+            return;
+        }
+        let Some(index) = self.settings_state.field_at(column, row) else {
+            return;
+        };
+        let fields = SettingsField::fields_for_provider(self.settings_tab);
+        let Some(&field) = fields.get(index) else {
+            return;
+        };
+
+        if self.settings_editing {
+            if index == self.settings_selected {
+                return;
+            }
+            // Clicking away from a field being edited keeps the edit
+            self.commit_settings_edit(&fields);
+        }
+
+        self.settings_selected = index;
+        self.settings_message = None;
+        if field.is_provider_selector() {
+            self.settings_config.provider.active = self.settings_config.provider.active.next();
+        } else {
+            self.activate_settings_field(field, tx);
+        }
+    }
+
+    /// Show another provider's settings, keeping a selected button selected
+    fn switch_settings_tab(&mut self, tab: ProviderType) {
+        let selected = SettingsField::fields_for_provider(self.settings_tab)
+            .get(self.settings_selected)
+            .copied();
+        let fields = SettingsField::fields_for_provider(tab);
+        self.settings_tab = tab;
+        self.settings_selected = selected
+            .and_then(|selected| fields.iter().position(|field| *field == selected))
+            .unwrap_or(1);
+        self.settings_message = None;
+    }
+
+    /// Snapshot the prompt before an edit so it can be undone.
+    ///
+    /// Consecutive edits of the same kind (typing, backspacing) are grouped
+    /// into a single undo step; `None` always starts a new step.
+    fn record_prompt_edit(&mut self, kind: Option<EditKind>) {
+        if kind.is_none() || kind != self.last_edit {
+            self.undo_stack.push((self.prompt.clone(), self.cursor_position));
+            if self.undo_stack.len() > UNDO_LIMIT {
+                self.undo_stack.remove(0);
+            }
+        }
+        self.redo_stack.clear();
+        self.last_edit = kind;
+    }
+
+    fn undo_prompt_edit(&mut self) {
+        if let Some((prompt, cursor)) = self.undo_stack.pop() {
+            let current = std::mem::replace(&mut self.prompt, prompt);
+            self.redo_stack.push((current, self.cursor_position));
+            self.cursor_position = cursor;
+            self.last_edit = None;
+            self.error = None;
+            self.update_command_menu();
+        }
+    }
+
+    fn redo_prompt_edit(&mut self) {
+        if let Some((prompt, cursor)) = self.redo_stack.pop() {
+            let current = std::mem::replace(&mut self.prompt, prompt);
+            self.undo_stack.push((current, self.cursor_position));
+            self.cursor_position = cursor;
+            self.last_edit = None;
+            self.error = None;
+            self.update_command_menu();
+        }
+    }
+
+    fn move_prompt_cursor(&mut self, position: usize) {
+        self.cursor_position = position;
+        self.last_edit = None;
+    }
+
+    /// Delete `start..end` from the prompt as a single undo step
+    fn delete_prompt_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        self.record_prompt_edit(None);
+        self.prompt.drain(start..end);
+        self.cursor_position = start;
+        self.error = None;
+        self.prompt_deleted();
+    }
+
+    /// Update command menu state after text was removed from the prompt
+    fn prompt_deleted(&mut self) {
+        if self.state == AppState::ShowingResults {
+            self.state = AppState::Idle;
+        }
+        self.update_command_menu();
+    }
+
+    /// Show command menu when prompt starts with /
+    fn update_command_menu(&mut self) {
+        if self.prompt.starts_with('/') {
+            self.command_filter = self.prompt.clone();
+            self.command_selected = 0;
+            // Hide if no matching commands
+            if self.filtered_commands().is_empty() {
+                self.state = AppState::Idle;
+                self.command_filter.clear();
+            } else {
+                self.state = AppState::CommandMenu;
+            }
+        } else if self.state == AppState::CommandMenu {
+            self.state = AppState::Idle;
+            self.command_filter.clear();
+        }
+    }
+
     fn filtered_commands(&self) -> Vec<Command> {
         let filter_lower = self.command_filter.to_lowercase();
         Command::all()
@@ -382,12 +798,15 @@ impl App {
             .collect()
     }
 
-    fn execute_command(&mut self, cmd: Command) {
+    fn execute_command(&mut self, cmd: Command, arg: &str) {
         match cmd {
             Command::Settings => {
-                self.settings_config = self.config.clone();
+                let previous = std::mem::replace(&mut self.settings_config, self.config.clone());
+                self.forget_stale_models(&previous);
+                self.settings_tab = self.config.provider.active;
                 self.settings_selected = 0;
                 self.settings_editing = false;
+                self.model_picker = None;
                 self.settings_message = None;
                 self.screen = Screen::Settings;
             }
@@ -395,6 +814,7 @@ impl App {
                 self.prompt.clear();
                 self.cursor_position = 0;
                 self.result = None;
+                self.analyzed_prompt = None;
                 self.error = None;
                 self.state = AppState::Idle;
             }
@@ -406,8 +826,71 @@ impl App {
                 self.config.provider.active = self.config.provider.active.next();
                 if let Err(e) = save_config(&self.config) {
                     self.error = Some(format!("Failed to save config: {}", e));
+                } else if let Some(project) = self.project.as_ref().filter(|p| p.overrides_key("provider.active")) {
+                    self.notice = Some(format!(
+                        "Saved {} as the default provider, but {} sets the provider for this project",
+                        self.config.provider.active,
+                        project.path.display()
+                    ));
+                    self.notice_is_warning = false;
+                }
+            }
+            Command::Profile => {
+                // Cycle through profiles, switch to the named one, or create
+                // one with `/profile new <name>`
+                let (name, create) = match arg.split_once(' ') {
+                    Some(("new", name)) if !name.trim().is_empty() => (name.trim().to_string(), true),
+                    _ if arg == "new" => {
+                        self.error = Some("Usage: /profile new <name>".to_string());
+                        return;
+                    }
+                    _ if !arg.is_empty() => (arg.to_string(), false),
+                    _ => match next_profile(&self.config) {
+                        Some(name) => (name, false),
+                        None => {
+                            self.error = Some("No profiles defined. Use /profile new <name> to create one".to_string());
+                            return;
+                        }
+                    },
+                };
+
+                if create {
+                    if self.config.profiles.contains_key(&name) {
+                        self.error = Some(format!("Profile '{}' already exists", name));
+                        return;
+                    }
+                    create_profile(&mut self.config, &name);
+                } else if let Err(e) = switch_profile(&mut self.config, &name) {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+
+                if let Err(e) = save_config(&self.config) {
+                    self.error = Some(format!("Failed to save config: {}", e));
+                } else {
+                    let action = if create { "Created" } else { "Switched to" };
+                    self.notice = Some(format!("{} profile '{}'", action, name));
+                    self.notice_is_warning = false;
                 }
             }
+            Command::Copy => match (&self.result, &self.analyzed_prompt) {
+                (Some(result), Some(prompt)) => {
+                    let markdown = analysis_markdown(prompt, result);
+                    self.copy_text(&markdown, "analysis");
+                }
+                _ => self.error = Some("No analysis to copy".to_string()),
+            },
+            Command::CopyPrompt => match self.analyzed_prompt.clone() {
+                Some(prompt) => self.copy_text(&prompt, "prompt"),
+                None => self.error = Some("No analyzed prompt to copy".to_string()),
+            },
+            Command::CopyImprovements => match &self.result {
+                Some(result) if !result.improvements.is_empty() => {
+                    let list = markdown_list(&result.improvements);
+                    self.copy_text(&list, "improvements");
+                }
+                _ => self.error = Some("No improvements to copy".to_string()),
+            },
             Command::Default => {
                 match reset_config() {
                     Ok(config) => {
@@ -420,6 +903,32 @@ impl App {
                 }
             }
         }
+        self.update_active_config();
+    }
+
+    /// Reapply project overrides after the global config changes
+    fn update_active_config(&mut self) {
+        let Some(project) = &self.project else {
+            self.active_config = self.config.clone();
+            return;
+        };
+        match project.apply(&self.config) {
+            Ok(config) => self.active_config = config,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn copy_text(&mut self, text: &str, what: &str) {
+        match copy_to_clipboard(text) {
+            Ok(method) => {
+                self.error = None;
+                self.notice = Some(format!("Copied {} to clipboard via {}", what, method));
+                self.notice_is_warning = false;
+            }
+            Err(e) => {
+                self.error = Some(format!("Failed to copy {}: {}", what, e));
+            }
+        }
     }
 
     async fn start_analysis(&mut self, tx: mpsc::Sender<AsyncMessage>) {
@@ -436,12 +945,13 @@ impl App {
         self.analyzing_animation_frame = 0;
         self.animation_tick_counter = 0;
 
-        let config = self.config.clone();
+        let config = self.active_config.clone();
         let prompt = self.prompt.clone();
+        self.analyzed_prompt = Some(prompt.clone());
 
         tokio::spawn(async move {
             let result = async {
-                let provider = create_provider(&config)?;
+                let provider = create_provider(&config).await?;
                 provider.analyze(&prompt).await
             }
             .await;
@@ -450,75 +960,59 @@ impl App {
         });
     }
 
-    fn handle_settings_input(&mut self, key: KeyCode) -> Result<()> {
-        let fields = SettingsField::fields_for_provider(self.settings_config.provider.active);
+    fn handle_settings_input(&mut self, key: KeyEvent, tx: &mpsc::Sender<AsyncMessage>) -> Result<()> {
+        let fields = SettingsField::fields_for_provider(self.settings_tab);
 
-        if self.settings_editing {
+        if self.model_picker.is_some() {
+            self.handle_model_picker_input(key.code, &fields, tx);
+        } else if self.settings_editing {
             self.handle_settings_edit_input(key, &fields)?;
         } else {
-            self.handle_settings_nav_input(key, &fields)?;
+            self.handle_settings_nav_input(key.code, &fields, tx)?;
         }
 
         Ok(())
     }
 
-    fn handle_settings_nav_input(&mut self, key: KeyCode, fields: &[SettingsField]) -> Result<()> {
+    fn handle_settings_nav_input(
+        &mut self,
+        key: KeyCode,
+        fields: &[SettingsField],
+        tx: &mpsc::Sender<AsyncMessage>,
+    ) -> Result<()> {
         match key {
             KeyCode::Esc => {
                 self.screen = Screen::Main;
             }
-            KeyCode::Up => {
-                if self.settings_selected > 0 {
-                    self.settings_selected -= 1;
-                    self.settings_message = None;
-                }
+            KeyCode::Up if self.settings_selected > 0 => {
+                self.settings_selected -= 1;
+                self.settings_message = None;
             }
-            KeyCode::Down | KeyCode::Tab => {
-                if self.settings_selected < fields.len() - 1 {
-                    self.settings_selected += 1;
-                    self.settings_message = None;
-                }
+            KeyCode::Down | KeyCode::Tab if self.settings_selected < fields.len() - 1 => {
+                self.settings_selected += 1;
+                self.settings_message = None;
             }
             KeyCode::Left | KeyCode::Right => {
-                if let Some(field) = fields.get(self.settings_selected) {
-                    if field.is_provider_selector() {
-                        // Cycle provider
-                        if key == KeyCode::Right {
-                            self.settings_config.provider.active = self.settings_config.provider.active.next();
-                        } else {
-                            // Cycle backwards
-                            self.settings_config.provider.active = match self.settings_config.provider.active {
-                                ProviderType::Azure => ProviderType::Anthropic,
-                                ProviderType::OpenAI => ProviderType::Azure,
-                                ProviderType::Anthropic => ProviderType::OpenAI,
-                            };
-                        }
-                        // Reset selected field when provider changes
-                        self.settings_selected = 0;
-                    }
+                let on_selector = fields
+                    .get(self.settings_selected)
+                    .is_some_and(SettingsField::is_provider_selector);
+                if on_selector {
+                    // Change the provider used for analysis
+                    let active = self.settings_config.provider.active;
+                    self.settings_config.provider.active =
+                        if key == KeyCode::Right { active.next() } else { active.prev() };
+                } else {
+                    let tab = if key == KeyCode::Right {
+                        self.settings_tab.next()
+                    } else {
+                        self.settings_tab.prev()
+                    };
+                    self.switch_settings_tab(tab);
                 }
             }
             KeyCode::Enter => {
-                if let Some(field) = fields.get(self.settings_selected) {
-                    if *field == SettingsField::Save {
-                        // Save configuration
-                        self.config = self.settings_config.clone();
-                        if let Err(e) = save_config(&self.config) {
-                            self.settings_message = Some(format!("Error: {}", e));
-                            self.settings_is_error = true;
-                        } else {
-                            self.settings_message = Some("Settings saved!".to_string());
-                            self.settings_is_error = false;
-                            self.screen = Screen::Main;
-                        }
-                    } else if *field == SettingsField::Cancel {
-                        self.screen = Screen::Main;
-                    } else if !field.is_button() && !field.is_provider_selector() {
-                        // Start editing
-                        self.settings_editing = true;
-                        self.settings_edit_value = self.get_settings_field_value(field);
-                        self.settings_cursor = self.settings_edit_value.len();
-                    }
+                if let Some(&field) = fields.get(self.settings_selected) {
+                    self.activate_settings_field(field, tx);
                 }
             }
             _ => {}
@@ -526,44 +1020,203 @@ impl App {
         Ok(())
     }
 
-    fn handle_settings_edit_input(&mut self, key: KeyCode, fields: &[SettingsField]) -> Result<()> {
+    /// Press a button or start editing a text field
+    fn activate_settings_field(&mut self, field: SettingsField, tx: &mpsc::Sender<AsyncMessage>) {
+        if field == SettingsField::Test {
+            self.start_connection_test(tx);
+        } else if field == SettingsField::Save {
+            // Jump to the first invalid field instead of saving it
+            if let Some((tab, index, error)) = self.first_settings_error() {
+                self.settings_tab = tab;
+                self.settings_selected = index;
+                let label = SettingsField::fields_for_provider(tab)[index].label();
+                self.settings_message = Some(format!("Error: {} {}: {}", tab, label, error));
+                self.settings_is_error = true;
+                return;
+            }
+
+            // Save configuration
+            self.config = self.settings_config.clone();
+            self.update_active_config();
+            if let Err(e) = save_config(&self.config) {
+                self.settings_message = Some(format!("Error: {}", e));
+                self.settings_is_error = true;
+            } else {
+                self.settings_message = Some("Settings saved!".to_string());
+                self.settings_is_error = false;
+                self.screen = Screen::Main;
+            }
+        } else if field == SettingsField::Cancel {
+            self.screen = Screen::Main;
+        } else if let Some(provider) = field.model_provider() {
+            self.model_picker = Some(ModelPicker::default());
+            if !self.model_cache.contains_key(&provider) {
+                self.fetch_models(provider, tx);
+            }
+        } else if !field.is_button() && !field.is_provider_selector() {
+            // Start editing
+            self.settings_editing = true;
+            self.settings_edit_value = self.get_settings_field_value(&field);
+            self.settings_cursor = self.settings_edit_value.len();
+        }
+    }
+
+    /// First settings field on any tab whose value is invalid, with the problem
+    fn first_settings_error(&self) -> Option<(ProviderType, usize, String)> {
+        ProviderType::all().iter().find_map(|&tab| {
+            SettingsField::fields_for_provider(tab)
+                .iter()
+                .enumerate()
+                .find_map(|(index, field)| {
+                    let key = field.config_key()?;
+                    let value = get_config_value(&self.settings_config, key).ok()?;
+                    let error = validate_config_value(key, &value).err()?;
+                    Some((tab, index, error.to_string()))
+                })
+        })
+    }
+
+    /// Check the shown tab's unsaved settings with a minimal request in the background
+    fn start_connection_test(&mut self, tx: &mpsc::Sender<AsyncMessage>) {
+        if self.settings_testing {
+            return;
+        }
+        self.settings_testing = true;
+        let provider = self.settings_tab;
+        self.settings_message = Some(format!("Testing connection to {}...", provider));
+        self.settings_is_error = false;
+
+        let config = self.settings_config.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let client = create_provider_for(&config, provider).await?;
+                let start = Instant::now();
+                client.test_connection().await?;
+                Ok(start.elapsed())
+            }
+            .await;
+
+            let _ = tx.send(AsyncMessage::ConnectionTested(provider, result)).await;
+        });
+    }
+
+    /// List a provider's models in the background, using the unsaved settings
+    fn fetch_models(&mut self, provider: ProviderType, tx: &mpsc::Sender<AsyncMessage>) {
+        if self.models_loading == Some(provider) {
+            return;
+        }
+        self.models_loading = Some(provider);
+        self.model_cache.remove(&provider);
+
+        let config = self.settings_config.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let result = async { create_provider_for(&config, provider).await?.list_models().await }.await;
+            let _ = tx.send(AsyncMessage::ModelsListed(provider, result)).await;
+        });
+    }
+
+    fn handle_model_picker_input(
+        &mut self,
+        key: KeyCode,
+        fields: &[SettingsField],
+        tx: &mpsc::Sender<AsyncMessage>,
+    ) {
+        let Some(&field) = fields.get(self.settings_selected) else {
+            return;
+        };
+        let Some(provider) = field.model_provider() else {
+            return;
+        };
+        let Some(picker) = self.model_picker.as_mut() else {
+            return;
+        };
+        let models = self.model_cache.get(&provider).map(Vec::as_slice).unwrap_or_default();
+        let matches = picker.matches(models);
+
         match key {
+            KeyCode::Esc => self.model_picker = None,
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down if picker.selected + 1 < matches.len() => picker.selected += 1,
+            KeyCode::Enter => {
+                let value = matches
+                    .get(picker.selected)
+                    .map(|model| model.to_string())
+                    .unwrap_or_else(|| picker.filter.trim().to_string());
+                self.model_picker = None;
+                if value.is_empty() {
+                    return;
+                }
+                if let Err(e) = self.set_settings_field_value(&field, &value) {
+                    self.settings_message = Some(format!("Error: {}", e));
+                    self.settings_is_error = true;
+                }
+            }
+            KeyCode::Backspace => {
+                picker.filter.pop();
+                picker.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                picker.filter.push(c);
+                picker.selected = 0;
+            }
+            KeyCode::F(5) => {
+                self.settings_message = None;
+                self.fetch_models(provider, tx);
+            }
+            _ => {}
+        }
+    }
+
+    /// Store the edit buffer into the selected field and stop editing
+    fn commit_settings_edit(&mut self, fields: &[SettingsField]) {
+        if let Some(field) = fields.get(self.settings_selected) {
+            let value = self.settings_edit_value.clone();
+            if let Err(e) = self.set_settings_field_value(field, &value) {
+                // Keep editing so the value can be corrected
+                self.settings_message = Some(format!("Error: {}", e));
+                self.settings_is_error = true;
+                return;
+            }
+        }
+        self.settings_message = None;
+        self.settings_editing = false;
+        self.settings_reveal_until = None;
+        self.settings_edit_value.clear();
+    }
+
+    fn handle_settings_edit_input(&mut self, key: KeyEvent, fields: &[SettingsField]) -> Result<()> {
+        match key.code {
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let revealed = self.settings_reveal_until.is_some_and(|until| Instant::now() < until);
+                self.settings_reveal_until = (!revealed).then(|| Instant::now() + REVEAL_DURATION);
+            }
             KeyCode::Esc => {
                 self.settings_editing = false;
                 self.settings_edit_value.clear();
+                self.settings_reveal_until = None;
             }
             KeyCode::Enter => {
                 // Save the edited value
-                if let Some(field) = fields.get(self.settings_selected) {
-                    self.set_settings_field_value(field, self.settings_edit_value.clone());
-                }
-                self.settings_editing = false;
-                self.settings_edit_value.clear();
+                self.commit_settings_edit(fields);
             }
             KeyCode::Char(c) => {
                 self.settings_edit_value.insert(self.settings_cursor, c);
-                self.settings_cursor += 1;
+                self.settings_cursor += c.len_utf8();
             }
-            KeyCode::Backspace => {
-                if self.settings_cursor > 0 {
-                    self.settings_cursor -= 1;
-                    self.settings_edit_value.remove(self.settings_cursor);
-                }
+            KeyCode::Backspace if self.settings_cursor > 0 => {
+                self.settings_cursor = prev_char_boundary(&self.settings_edit_value, self.settings_cursor);
+                self.settings_edit_value.remove(self.settings_cursor);
             }
-            KeyCode::Delete => {
-                if self.settings_cursor < self.settings_edit_value.len() {
-                    self.settings_edit_value.remove(self.settings_cursor);
-                }
+            KeyCode::Delete if self.settings_cursor < self.settings_edit_value.len() => {
+                self.settings_edit_value.remove(self.settings_cursor);
             }
-            KeyCode::Left => {
-                if self.settings_cursor > 0 {
-                    self.settings_cursor -= 1;
-                }
+            KeyCode::Left if self.settings_cursor > 0 => {
+                self.settings_cursor = prev_char_boundary(&self.settings_edit_value, self.settings_cursor);
             }
-            KeyCode::Right => {
-                if self.settings_cursor < self.settings_edit_value.len() {
-                    self.settings_cursor += 1;
-                }
+            KeyCode::Right if self.settings_cursor < self.settings_edit_value.len() => {
+                self.settings_cursor = next_char_boundary(&self.settings_edit_value, self.settings_cursor);
             }
             KeyCode::Home => {
                 self.settings_cursor = 0;
@@ -577,30 +1230,238 @@ impl App {
     }
 
     fn get_settings_field_value(&self, field: &SettingsField) -> String {
-        match field {
-            SettingsField::AzureUrl => self.settings_config.azure.url.clone(),
-            SettingsField::AzureApiKey => self.settings_config.azure.api_key.clone(),
-            SettingsField::AzureDeployment => self.settings_config.azure.deployment.clone(),
-            SettingsField::AzureApiVersion => self.settings_config.azure.api_version.clone(),
-            SettingsField::OpenAIApiKey => self.settings_config.openai.api_key.clone(),
-            SettingsField::OpenAIModel => self.settings_config.openai.model.clone(),
-            SettingsField::AnthropicApiKey => self.settings_config.anthropic.api_key.clone(),
-            SettingsField::AnthropicModel => self.settings_config.anthropic.model.clone(),
-            _ => String::new(),
-        }
-    }
-
-    fn set_settings_field_value(&mut self, field: &SettingsField, value: String) {
-        match field {
-            SettingsField::AzureUrl => self.settings_config.azure.url = value,
-            SettingsField::AzureApiKey => self.settings_config.azure.api_key = value,
-            SettingsField::AzureDeployment => self.settings_config.azure.deployment = value,
-            SettingsField::AzureApiVersion => self.settings_config.azure.api_version = value,
-            SettingsField::OpenAIApiKey => self.settings_config.openai.api_key = value,
-            SettingsField::OpenAIModel => self.settings_config.openai.model = value,
-            SettingsField::AnthropicApiKey => self.settings_config.anthropic.api_key = value,
-            SettingsField::AnthropicModel => self.settings_config.anthropic.model = value,
-            _ => {}
+        field
+            .config_key()
+            .and_then(|key| get_config_value(&self.settings_config, key).ok())
+            .unwrap_or_default()
+    }
+
+    fn set_settings_field_value(&mut self, field: &SettingsField, value: &str) -> Result<()> {
+        let Some(key) = field.config_key() else {
+            return Ok(());
+        };
+        let previous = self.settings_config.clone();
+        set_config_value_unchecked(&mut self.settings_config, key, value)?;
+        self.forget_stale_models(&previous);
+        Ok(())
+    }
+
+    /// Drop model lists fetched with a provider's old key or URL
+    fn forget_stale_models(&mut self, previous: &Config) {
+        for &provider in ProviderType::all() {
+            if model_list_settings(previous, provider) != model_list_settings(&self.settings_config, provider) {
+                self.model_cache.remove(&provider);
+                if self.models_loading == Some(provider) {
+                    self.models_loading = None;
+                }
+            }
+        }
+    }
+}
+
+/// Settings a provider's model list depends on: its endpoint and API key
+fn model_list_settings(config: &Config, provider: ProviderType) -> (&str, (&str, &str, &str)) {
+    let url = match provider {
+        ProviderType::Azure => config.azure.url.as_str(),
+        ProviderType::OpenAI | ProviderType::Anthropic => "",
+    };
+    (url, key_fields(config, provider))
+}
+
+/// Format an analysis as a Markdown document
+fn analysis_markdown(prompt: &str, result: &AnalysisResult) -> String {
+    let mut markdown = format!(
+        "# Prompt Analysis\n\n**Score:** {}/100 ({})\n\n## Prompt\n\n```\n{}\n```\n",
+        result.score,
+        score_label(result.score),
+        prompt
+    );
+    if !result.improvements.is_empty() {
+        markdown.push_str("\n## Improvements\n\n");
+        markdown.push_str(&markdown_list(&result.improvements));
+    }
+    if !result.unclear_parts.is_empty() {
+        markdown.push_str("\n## Unclear Parts\n\n");
+        markdown.push_str(&markdown_list(&result.unclear_parts));
+    }
+    markdown
+}
+
+fn markdown_list(items: &[String]) -> String {
+    items.iter().map(|item| format!("- {}\n", item)).collect()
+}
+
+/// Ctrl held without Alt. Windows reports AltGr as Ctrl+Alt, and AltGr
+/// characters such as `@` or `{` must still be typed.
+fn is_ctrl(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.contains(KeyModifiers::ALT)
+}
+
+/// Alt held without Ctrl, for the same AltGr reason as `is_ctrl`
+fn is_alt(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::ALT) && !key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+fn prev_char_boundary(text: &str, pos: usize) -> usize {
+    text[..pos].char_indices().next_back().map(|(i, _)| i).unwrap_or(0)
+}
+
+fn next_char_boundary(text: &str, pos: usize) -> usize {
+    text[pos..].chars().next().map(|c| pos + c.len_utf8()).unwrap_or(pos)
+}
+
+/// Byte offset of the start of the line containing `pos`
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+/// Byte offset of the start of the word before `pos`, skipping whitespace first
+fn prev_word_start(text: &str, pos: usize) -> usize {
+    let before = text[..pos].trim_end();
+    before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+/// Byte offset of the end of the word after `pos`, skipping whitespace first
+fn next_word_end(text: &str, pos: usize) -> usize {
+    let after = &text[pos..];
+    let word_offset = after.len() - after.trim_start().len();
+    after[word_offset..]
+        .char_indices()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(i, _)| pos + word_offset + i)
+        .unwrap_or(text.len())
+}
+
+/// Byte offset of the char at `column` in `line`, clamped to the line end
+fn column_offset(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+/// Byte offset of `column` on line `line`, clamped to the text
+fn position_at(text: &str, line: usize, column: usize) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
         }
     }
+    let end = text[start..].find('\n').map(|i| start + i).unwrap_or(text.len());
+    start + column_offset(&text[start..end], column)
+}
+
+/// Cursor position on the previous line, keeping the column where possible
+fn line_up(text: &str, pos: usize) -> Option<usize> {
+    let start = line_start(text, pos);
+    if start == 0 {
+        return None;
+    }
+    let column = text[start..pos].chars().count();
+    let prev_start = line_start(text, start - 1);
+    Some(prev_start + column_offset(&text[prev_start..start - 1], column))
+}
+
+/// Cursor position on the next line, keeping the column where possible
+fn line_down(text: &str, pos: usize) -> Option<usize> {
+    let start = line_start(text, pos);
+    let column = text[start..pos].chars().count();
+    let next_start = pos + text[pos..].find('\n')? + 1;
+    let next_end = text[next_start..]
+        .find('\n')
+        .map(|i| next_start + i)
+        .unwrap_or(text.len());
+    Some(next_start + column_offset(&text[next_start..next_end], column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_altgr_is_not_ctrl() {
+        let key = |modifiers| KeyEvent::new(KeyCode::Char('@'), modifiers);
+        assert!(is_ctrl(&key(KeyModifiers::CONTROL)));
+        assert!(!is_ctrl(&key(KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert!(is_alt(&key(KeyModifiers::ALT)));
+        assert!(!is_alt(&key(KeyModifiers::CONTROL | KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn test_settings_paste_non_ascii() {
+        let mut app = App::with_config(Config::default(), None).unwrap();
+        app.screen = Screen::Settings;
+        app.settings_tab = ProviderType::Azure;
+        let fields = SettingsField::fields_for_provider(ProviderType::Azure);
+        app.settings_selected = fields.iter().position(|f| *f == SettingsField::AzureUrl).unwrap();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_settings_paste("https://é");
+        assert_eq!(app.settings_cursor, "https://é".len());
+        app.handle_settings_edit_input(key(KeyCode::Left), &fields).unwrap();
+        app.handle_settings_edit_input(key(KeyCode::Right), &fields).unwrap();
+        app.handle_settings_edit_input(key(KeyCode::Backspace), &fields).unwrap();
+        assert_eq!(app.settings_edit_value, "https://");
+        assert_eq!(app.settings_cursor, 8);
+        app.handle_settings_edit_input(key(KeyCode::Char('ü')), &fields).unwrap();
+        app.handle_settings_edit_input(key(KeyCode::Left), &fields).unwrap();
+        app.handle_settings_edit_input(key(KeyCode::Delete), &fields).unwrap();
+        assert_eq!(app.settings_edit_value, "https://");
+    }
+
+    #[test]
+    fn test_profile_command_needs_existing_profile() {
+        let mut app = App::with_config(Config::default(), None).unwrap();
+        app.execute_command(Command::Profile, "wrok");
+        assert!(app.error.as_deref().unwrap().contains("Unknown profile 'wrok'"));
+        app.execute_command(Command::Profile, "new");
+        assert_eq!(app.error.as_deref(), Some("Usage: /profile new <name>"));
+        assert!(app.config.profiles.is_empty());
+    }
+
+    #[test]
+    fn test_analysis_markdown() {
+        let result = AnalysisResult {
+            score: 42,
+            improvements: vec!["Add context".to_string()],
+            unclear_parts: Vec::new(),
+        };
+        assert_eq!(
+            analysis_markdown("Write code", &result),
+            "# Prompt Analysis\n\n**Score:** 42/100 (Poor)\n\n## Prompt\n\n```\nWrite code\n```\n\n## Improvements\n\n- Add context\n"
+        );
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let text = "fix the  parser\nnow";
+        assert_eq!(prev_word_start(text, 15), 9);
+        assert_eq!(prev_word_start(text, 9), 4);
+        assert_eq!(prev_word_start(text, 2), 0);
+        assert_eq!(next_word_end(text, 3), 7);
+        assert_eq!(next_word_end(text, 7), 15);
+        assert_eq!(next_word_end(text, 15), 19);
+    }
+
+    #[test]
+    fn test_line_navigation() {
+        let text = "first line\nab\nthird";
+        // From column 5 on the first line down to the end of the short line
+        assert_eq!(line_down(text, 5), Some(13));
+        assert_eq!(line_down(text, 12), Some(15));
+        assert_eq!(line_down(text, 16), None);
+        assert_eq!(line_up(text, 17), Some(13));
+        assert_eq!(line_up(text, 13), Some(2));
+        assert_eq!(line_up(text, 3), None);
+        assert_eq!(position_at(text, 1, 9), 13);
+        assert_eq!(position_at(text, 2, 1), 15);
+        assert_eq!(position_at(text, 5, 0), text.len());
+    }
 }
@@ -0,0 +1,90 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Local clipboard utilities, tried in order: (program, args, required env var)
+const LOCAL_CLIPBOARDS: &[(&str, &[&str], Option<&str>)] = &[
+    ("pbcopy", &[], None),
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+];
+
+/// Copy text to the system clipboard.
+///
+/// The text is always sent to the terminal with OSC 52, which also works
+/// over SSH. On a local session it is additionally piped to the first
+/// available clipboard utility, since not every terminal honours OSC 52.
+/// Returns a short description of how the text was copied.
+pub fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(osc52_sequence(text, env::var_os("TMUX").is_some()).as_bytes())
+        .and_then(|_| stdout.flush())
+        .context("Failed to write to the terminal")?;
+
+    if is_ssh_session() {
+        return Ok("terminal (OSC 52)");
+    }
+
+    for (program, args, required_env) in LOCAL_CLIPBOARDS {
+        if required_env.is_some_and(|var| env::var_os(var).is_none()) {
+            continue;
+        }
+        if pipe_to(program, args, text).is_ok() {
+            return Ok(program);
+        }
+    }
+
+    Ok("terminal (OSC 52)")
+}
+
+fn is_ssh_session() -> bool {
+    env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some()
+}
+
+/// Build the OSC 52 escape sequence, wrapped for tmux passthrough if needed
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        format!("\x1bPtmux;\x1b{}\x1b\\", sequence)
+    } else {
+        sequence
+    }
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write to {}", program))?;
+    }
+
+    let status = child.wait().with_context(|| format!("Failed to wait for {}", program))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
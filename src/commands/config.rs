@@ -0,0 +1,150 @@
+use std::env;
+use std::io::{self, Read};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+
+use crate::config::{
+    config_path, get_config_value, is_secret_key, load_config, load_project_config, save_config,
+    set_config_value, use_profile_for_run, ProjectConfig, CONFIG_KEYS,
+};
+use crate::secrets::{key_source, mask_secret};
+use crate::types::{Config, ProviderType};
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print a value, e.g. `mahoraga config get openai.model`
+    Get {
+        key: String,
+        /// Print API keys instead of masking them
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Set a value. Pass `-` as the value to read it from stdin
+    Set { key: String, value: String },
+    /// List all values in effect, with API keys masked
+    List,
+    /// Print the path of the config file
+    Path,
+    /// Open the config file in $VISUAL or $EDITOR
+    Edit,
+}
+
+pub fn run_config(action: ConfigAction, profile: Option<&str>) -> Result<()> {
+    match action {
+        ConfigAction::Get { key, reveal } => {
+            let (config, _) = load_effective(profile)?;
+            let value = get_config_value(&config, &key)?;
+            if is_secret_key(&key) && !reveal {
+                println!("{}", mask_secret(&value));
+            } else {
+                println!("{}", value);
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let value = if value == "-" { read_stdin()? } else { value };
+            let mut config = load_with_profile(profile)?;
+            set_config_value(&mut config, &key, &value)?;
+            save_config(&config)?;
+            if is_secret_key(&key) {
+                println!("Set {}", key);
+            } else {
+                println!("Set {} = {}", key, value);
+            }
+        }
+        ConfigAction::List => {
+            let (effective, project) = load_effective(profile)?;
+
+            if !effective.active_profile.is_empty() {
+                println!("# profile: {}", effective.active_profile);
+            }
+            for key in CONFIG_KEYS {
+                let value = get_config_value(&effective, key)?;
+                let mut line = if is_secret_key(key) {
+                    format!("{} = {}", key, mask_secret(&value))
+                } else {
+                    format!("{} = {}", key, value)
+                };
+                if let Some(provider) = key_provider(key).filter(|_| is_secret_key(key)) {
+                    line.push_str(&format!("  # {}", key_source(&effective, provider).describe()));
+                } else if let Some(project) = project.as_ref().filter(|project| project.overrides_key(key)) {
+                    line.push_str(&format!("  # from {}", project.path.display()));
+                }
+                println!("{}", line.trim_end());
+            }
+        }
+        ConfigAction::Path => {
+            println!("{}", config_path()?.display());
+        }
+        ConfigAction::Edit => edit_config()?,
+    }
+
+    Ok(())
+}
+
+/// Load the config, switching to the given profile first
+fn load_with_profile(profile: Option<&str>) -> Result<Config> {
+    let mut config = load_config()?;
+    if let Some(name) = profile {
+        use_profile_for_run(&mut config, name)?;
+    }
+    Ok(config)
+}
+
+/// The config in effect in the current directory: the profile's, with any
+/// project `.mahoraga.toml` merged over it
+fn load_effective(profile: Option<&str>) -> Result<(Config, Option<ProjectConfig>)> {
+    let config = load_with_profile(profile)?;
+    let project = load_project_config()?;
+    let effective = match &project {
+        Some(project) => project.apply(&config)?,
+        None => config,
+    };
+    Ok((effective, project))
+}
+
+/// Provider a dotted key belongs to, e.g. `openai.model`
+fn key_provider(key: &str) -> Option<ProviderType> {
+    let section = key.split_once('.')?.0;
+    ProviderType::all()
+        .iter()
+        .copied()
+        .find(|provider| provider.as_str() == section)
+}
+
+/// Read a value from stdin, so secrets stay out of shell history
+fn read_stdin() -> Result<String> {
+    let mut value = String::new();
+    io::stdin()
+        .read_to_string(&mut value)
+        .context("Failed to read value from stdin")?;
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Open the config file in the user's editor, then check it still loads
+fn edit_config() -> Result<()> {
+    let path = config_path()?;
+    if !path.exists() {
+        save_config(&Config::default())?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    // The editor may include arguments, e.g. `code --wait`
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("Editor command is empty")?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run editor: {}", editor))?;
+    if !status.success() {
+        bail!("Editor exited with {}", status);
+    }
+
+    load_config().context("The edited config file has errors")?;
+    Ok(())
+}
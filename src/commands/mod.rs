@@ -1,5 +1,7 @@
+mod config;
 mod uninstall;
 mod update;
 
-pub use uninstall::run_uninstall;
-pub use update::run_update;
+pub use config::{run_config, ConfigAction};
+pub use uninstall::{run_uninstall, UninstallArgs};
+pub use update::{check_for_update, run_update, UpdateArgs};
@@ -1,29 +1,174 @@
 use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use clap::Args;
 
-pub fn run_uninstall() -> Result<()> {
+use crate::config::{config_dir, config_path};
+use crate::secrets::{keyring_accounts, KeyringStore, SecretStore, KEYRING_SERVICE};
+
+#[derive(Args)]
+pub struct UninstallArgs {
+    /// Remove without asking for confirmation
+    #[arg(long, short)]
+    pub yes: bool,
+    /// Show what would be removed without removing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Keep the config directory and the API keys stored in the OS keyring
+    /// (there is no history to keep: mahoraga doesn't store any)
+    #[arg(long)]
+    pub keep_config: bool,
+}
+
+/// Where cargo installs binaries: `$CARGO_HOME/bin`, or `~/.cargo/bin`
+fn cargo_bin_dir() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+        .map(|cargo_home| cargo_home.join("bin"))
+}
+
+/// How to uninstall a binary that another tool owns, if one does.
+///
+/// Deleting such a binary by hand leaves the tool's records behind, so the
+/// next upgrade or removal through it fails.
+fn package_manager_hint(exe: &Path, cargo_bin: Option<&Path>) -> Option<&'static str> {
+    let dir = exe.parent()?;
+    if cargo_bin.is_some_and(|cargo_bin| dir == cargo_bin) {
+        Some("It was installed with cargo. Run `cargo uninstall mahoraga` instead.")
+    } else if exe.components().any(|c| c.as_os_str() == "Cellar") {
+        Some("It was installed with Homebrew. Run `brew uninstall mahoraga` instead.")
+    } else if exe.starts_with("/nix/store") {
+        Some("It was installed with Nix. Remove it from your profile or configuration instead.")
+    } else if ["/usr/bin", "/bin", "/usr/sbin"].iter().any(|system| dir == Path::new(system)) {
+        Some("It is managed by the system package manager. Remove it with apt, dnf or pacman instead.")
+    } else {
+        None
+    }
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        bail!("Refusing to uninstall without confirmation. Pass --yes to proceed.");
+    }
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+pub fn run_uninstall(args: UninstallArgs) -> Result<()> {
     let exe_path = std::env::current_exe().context("Failed to determine current executable path")?;
 
-    // Remove config directory
-    if let Some(config_dir) = dirs::config_dir() {
-        let mahoraga_config = config_dir.join("mahoraga");
-        if mahoraga_config.exists() {
-            fs::remove_dir_all(&mahoraga_config).context("Failed to remove config directory")?;
-            println!("Removed config directory: {}", mahoraga_config.display());
-        } else {
-            println!("No config directory found at {}", mahoraga_config.display());
+    // Resolve symlinks, e.g. Homebrew links bin/mahoraga into its Cellar
+    let real_exe = fs::canonicalize(&exe_path).unwrap_or_else(|_| exe_path.clone());
+    if let Some(hint) = package_manager_hint(&real_exe, cargo_bin_dir().as_deref()) {
+        bail!("Not removing {}. {}", real_exe.display(), hint);
+    }
+
+    // Everything to remove, with a description for the confirmation list.
+    // The binary goes first: if it can't be removed, e.g. without sudo,
+    // nothing else has been lost yet.
+    let mut targets: Vec<(PathBuf, &str)> = vec![(exe_path.clone(), "binary")];
+    let backup = exe_path.with_file_name("mahoraga.bak");
+    if backup.exists() {
+        targets.push((backup, "previous version kept by `mahoraga update`"));
+    }
+
+    let mut keyring_entries = Vec::new();
+    let mahoraga_config = config_dir().ok();
+    match &mahoraga_config {
+        Some(dir) if args.keep_config => {
+            println!("Keeping config directory and stored API keys: {}", dir.display());
         }
+        Some(dir) if dir.exists() => {
+            // Unreadable configs can't point at any entries
+            keyring_entries = config_path()
+                .and_then(|path| Ok(fs::read_to_string(path)?))
+                .ok()
+                .and_then(|content| content.parse::<toml::Table>().ok())
+                .map(|config| keyring_accounts(&config))
+                .unwrap_or_default();
+            targets.push((dir.clone(), "config directory with settings and stored API keys"));
+        }
+        Some(dir) => println!("No config directory found at {}", dir.display()),
+        None => {}
+    }
+
+    println!("The following will be removed:");
+    for (path, description) in &targets {
+        println!("  {}  ({})", path.display(), description);
+    }
+    for account in &keyring_entries {
+        println!("  OS keyring entry {}/{}  (stored API key)", KEYRING_SERVICE, account);
     }
 
-    // Remove the binary itself
-    // On Unix, this is safe because the running binary stays in memory
-    fs::remove_file(&exe_path).context(
-        "Failed to remove binary. You may need to run with elevated permissions (e.g., sudo).",
-    )?;
-    println!("Removed binary: {}", exe_path.display());
+    if args.dry_run {
+        println!("Dry run: nothing was removed.");
+        return Ok(());
+    }
+    if !args.yes && !confirm("Uninstall mahoraga?")? {
+        println!("Uninstall cancelled.");
+        return Ok(());
+    }
+
+    // Keyring entries go before the config that names them, so a failure
+    // leaves a way to find them
+    let (files, config_dirs): (Vec<_>, Vec<_>) = targets.iter().partition(|(path, _)| !path.is_dir());
+    for (path, _) in &files {
+        // On Unix, this is safe because the running binary stays in memory
+        fs::remove_file(path).with_context(|| {
+            format!(
+                "Failed to remove {}. You may need to run with elevated permissions (e.g., sudo).",
+                path.display()
+            )
+        })?;
+        println!("Removed: {}", path.display());
+    }
+    let store = KeyringStore;
+    for account in &keyring_entries {
+        store
+            .delete(account)
+            .with_context(|| {
+                format!(
+                    "Failed to remove OS keyring entry {}/{}. The config directory was kept; \
+                     remove it by hand once the keyring entries are gone.",
+                    KEYRING_SERVICE, account
+                )
+            })?;
+        println!("Removed OS keyring entry: {}/{}", KEYRING_SERVICE, account);
+    }
+    for (path, _) in &config_dirs {
+        fs::remove_dir_all(path).context("Failed to remove config directory")?;
+        println!("Removed config directory: {}", path.display());
+    }
 
     println!("Mahoraga has been uninstalled successfully.");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_manager_hint() {
+        let cargo_bin = Path::new("/home/me/.cargo/bin");
+        let hint = |exe: &str| package_manager_hint(Path::new(exe), Some(cargo_bin));
+
+        assert!(hint("/home/me/.cargo/bin/mahoraga").unwrap().contains("cargo uninstall"));
+        assert!(hint("/opt/homebrew/Cellar/mahoraga/0.1.2/bin/mahoraga").unwrap().contains("brew"));
+        assert!(hint("/usr/bin/mahoraga").is_some());
+        assert!(hint("/home/me/.local/bin/mahoraga").is_none());
+        assert!(hint("/usr/local/bin/mahoraga").is_none());
+    }
+}
@@ -1,19 +1,86 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 #[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
 
 use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
 use flate2::read::GzDecoder;
-use serde::Deserialize;
+use minisign_verify::{PublicKey, Signature};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tar::Archive;
 
+use crate::config::{config_dir, config_path, validate_config_value};
+
 const GITHUB_RELEASES_URL: &str =
-    "https://api.github.com/repos/wyMinLwin/mahoraga/releases/latest";
+    "https://api.github.com/repos/wyMinLwin/mahoraga/releases";
+
+/// Environment variable that overrides `update.release_url`
+const RELEASE_URL_ENV: &str = "MAHORAGA_RELEASE_URL";
+
+/// Minisign key that release archives are signed with. The secret half lives
+/// only in the release workflow's `MINISIGN_SECRET_KEY` secret, encrypted with
+/// `MINISIGN_PASSWORD`.
+const RELEASE_PUBLIC_KEY: &str = "RWTHKvOzMGjJCcVDMH5X3umsyT7Hu1UVvmPLxgmAIcrh7qDYLRMmH76Y";
+
+/// Name of the previous binary kept next to the executable after an update
+const BACKUP_FILE: &str = "mahoraga.bak";
+
+/// File in the config dir remembering the last background update check
+const UPDATE_CHECK_FILE: &str = "update-check.toml";
+
+/// How often the TUI looks for a new release
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Give up on the background check quickly on slow or offline networks
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exit code of `update --check` when an update is available. Up to date
+/// exits with 0 and errors with 1.
+const EXIT_UPDATE_AVAILABLE: u8 = 10;
+
+#[derive(Args)]
+pub struct UpdateArgs {
+    /// Restore the binary that was replaced by the last update
+    #[arg(long, conflicts_with_all = ["version", "channel", "check", "dry_run"])]
+    pub rollback: bool,
+    /// Only report whether an update is available, as JSON. Exits with 10
+    /// when there is one, 0 when up to date and 1 on errors
+    #[arg(long, conflicts_with = "dry_run")]
+    pub check: bool,
+    /// Download and verify the update without installing it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Install a specific release, e.g. v1.2.0. Older versions are allowed
+    #[arg(long, value_name = "TAG")]
+    pub version: Option<String>,
+    /// Release channel to follow when no version is given
+    #[arg(long, value_enum, default_value_t = Channel::Stable, conflicts_with = "version")]
+    pub channel: Channel,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Channel {
+    /// Full releases only
+    Stable,
+    /// Full releases and pre-releases
+    Beta,
+}
 
 #[derive(Deserialize)]
 struct Release {
     tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
     assets: Vec<Asset>,
 }
 
@@ -37,59 +104,445 @@ fn platform_target() -> Result<&'static str> {
     }
 }
 
-fn parse_version(v: &str) -> Result<(u64, u64, u64)> {
-    let v = v.strip_prefix('v').unwrap_or(v);
-    let parts: Vec<&str> = v.split('.').collect();
+/// Download a release asset, failing on HTTP errors
+async fn download(client: &reqwest::Client, asset: &Asset) -> Result<Vec<u8>> {
+    let bytes = client
+        .get(&asset.browser_download_url)
+        .header("User-Agent", "mahoraga-updater")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to download {}", asset.name))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read {}", asset.name))?;
+    Ok(bytes.to_vec())
+}
+
+/// Check an archive against a `sha256sum`-style checksum file
+fn verify_checksum(archive: &[u8], checksum_file: &str, archive_name: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .with_context(|| format!("Checksum file for {archive_name} is empty"))?
+        .to_lowercase();
+    let actual: String = Sha256::digest(archive)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    if expected != actual {
+        bail!(
+            "Checksum mismatch for {archive_name}: expected {expected}, got {actual}. \
+             The download may be corrupted or tampered with; nothing was installed."
+        );
+    }
+    Ok(())
+}
+
+/// Check an archive's detached minisign signature against a public key.
+///
+/// The signed trusted comment must name the archive and the release it was
+/// built for, so an older signed archive can't be passed off as a new one.
+fn verify_signature(archive: &[u8], signature: &str, public_key: &str, archive_name: &str, version: &str) -> Result<()> {
+    let public_key = PublicKey::from_base64(public_key).context("Invalid release public key")?;
+    let signature = Signature::decode(signature)
+        .with_context(|| format!("Invalid signature file for {archive_name}"))?;
+    public_key.verify(archive, &signature, false).map_err(|_| {
+        anyhow::anyhow!(
+            "Signature verification failed for {archive_name}. \
+             The release was not signed by the mahoraga maintainers; nothing was installed."
+        )
+    })?;
+
+    let comment = signature.trusted_comment();
+    let field = |key: &str| {
+        comment
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix(key)?.strip_prefix(':'))
+    };
+    if field("file") != Some(archive_name) || field("version") != Some(version) {
+        bail!(
+            "Signature for {archive_name} was made for a different release ({comment}); nothing was installed."
+        );
+    }
+    Ok(())
+}
+
+/// Find a release asset by exact name
+fn find_asset<'a>(release: &'a Release, name: &str) -> Result<&'a Asset> {
+    release
+        .assets
+        .iter()
+        .find(|a| a.name == name)
+        .with_context(|| format!("Release {} has no {name}; refusing to install an unverified binary", release.tag_name))
+}
+
+/// Path of the executable and of its backup
+fn exe_paths() -> Result<(PathBuf, PathBuf)> {
+    let current_exe = std::env::current_exe().context("Failed to determine current executable path")?;
+    let exe_dir = current_exe
+        .parent()
+        .context("Failed to determine executable directory")?;
+    let backup = exe_dir.join(BACKUP_FILE);
+    Ok((current_exe, backup))
+}
+
+/// Atomically move `src` over the executable
+fn replace_binary(src: &Path, exe: &Path) -> Result<()> {
+    fs::rename(src, exe).context(
+        "Failed to replace binary. You may need to run with elevated permissions (e.g., sudo).",
+    )
+}
+
+/// Create a new directory under the system temp dir that only we can use.
+///
+/// The name is random and creation fails if it already exists, so nothing
+/// planted in the shared temp dir beforehand can be written through.
+fn private_temp_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("mahoraga-update-{:016x}", rand::random::<u64>()));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder
+        .create(&dir)
+        .with_context(|| format!("Failed to create temporary directory {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Write an executable file, failing if anything already exists at `path`
+fn write_new_executable(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o755);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Run `<exe> --version` and check it reports the expected version
+fn smoke_test(exe: &Path, expected_version: &str) -> Result<()> {
+    let output = Command::new(exe)
+        .arg("--version")
+        .output()
+        .context("Failed to run the new binary")?;
+    if !output.status.success() {
+        bail!("`mahoraga --version` exited with {}", output.status);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = expected_version.strip_prefix('v').unwrap_or(expected_version);
+    if !stdout.split_whitespace().any(|word| word == expected) {
+        bail!("`mahoraga --version` printed '{}', expected {}", stdout.trim(), expected);
+    }
+    Ok(())
+}
+
+/// Restore the binary saved by the last update
+fn rollback() -> Result<()> {
+    let (current_exe, backup) = exe_paths()?;
+    if !backup.exists() {
+        bail!("No previous version to roll back to ({} not found)", backup.display());
+    }
+
+    replace_binary(&backup, &current_exe)?;
+    println!("Rolled back to the previous version ({}).", current_exe.display());
+    Ok(())
+}
+
+/// Pre-release identifier; numeric ones sort before alphanumeric ones
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Prerelease {
+    Numeric(u64),
+    Alpha(String),
+}
+
+/// Semantic version. Build metadata is accepted but ignored, as semver requires
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Prerelease>,
+}
+
+impl Version {
+    fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release outranks any of its pre-releases
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, id) in self.pre.iter().enumerate() {
+            f.write_str(if i == 0 { "-" } else { "." })?;
+            match id {
+                Prerelease::Numeric(n) => write!(f, "{n}")?,
+                Prerelease::Alpha(s) => f.write_str(s)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check a dot-separated list of identifiers: non-empty, `[0-9A-Za-z-]` only
+fn valid_identifiers(ids: &str) -> bool {
+    ids.split('.').all(|id| {
+        !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+fn parse_number(part: &str, name: &str, v: &str) -> Result<u64> {
+    if part.len() > 1 && part.starts_with('0') {
+        bail!("Invalid {name} version in {v}: leading zeros are not allowed");
+    }
+    part.parse::<u64>()
+        .with_context(|| format!("Invalid {name} version in {v}"))
+}
+
+fn parse_version(v: &str) -> Result<Version> {
+    let version = v.strip_prefix('v').unwrap_or(v);
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+    if build.is_some_and(|build| !valid_identifiers(build)) {
+        bail!("Invalid build metadata in {v}");
+    }
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+
+    let parts: Vec<&str> = core.split('.').collect();
     if parts.len() != 3 {
         bail!("Invalid version format: {v}");
     }
-    let major = parts[0].parse::<u64>().context("Invalid major version")?;
-    let minor = parts[1].parse::<u64>().context("Invalid minor version")?;
-    let patch = parts[2].parse::<u64>().context("Invalid patch version")?;
-    Ok((major, minor, patch))
+    let major = parse_number(parts[0], "major", v)?;
+    let minor = parse_number(parts[1], "minor", v)?;
+    let patch = parse_number(parts[2], "patch", v)?;
+
+    let pre = match pre {
+        Some(pre) if !valid_identifiers(pre) => bail!("Invalid pre-release in {v}"),
+        Some(pre) => pre
+            .split('.')
+            .map(|id| {
+                if id.chars().all(|c| c.is_ascii_digit()) {
+                    parse_number(id, "pre-release", v).map(Prerelease::Numeric)
+                } else {
+                    Ok(Prerelease::Alpha(id.to_string()))
+                }
+            })
+            .collect::<Result<_>>()?,
+        None => Vec::new(),
+    };
+
+    Ok(Version { major, minor, patch, pre })
 }
 
-pub async fn run_update() -> Result<()> {
-    let current_version = env!("CARGO_PKG_VERSION");
-    println!("Current version: v{current_version}");
-    println!("Checking for updates...");
+/// Releases API to update from: `$MAHORAGA_RELEASE_URL`, then
+/// `update.release_url`, then GitHub.
+///
+/// A mirror must serve GitHub's layout: `<url>/latest`, `<url>/tags/<tag>`
+/// and the release list at `<url>`, with asset download URLs inside.
+fn releases_url() -> Result<String> {
+    let (url, source) = match std::env::var(RELEASE_URL_ENV) {
+        Ok(url) if !url.is_empty() => (url, RELEASE_URL_ENV),
+        _ => (configured_release_url(), "update.release_url"),
+    };
+    if url.is_empty() {
+        return Ok(GITHUB_RELEASES_URL.to_string());
+    }
+    validate_config_value("update.release_url", &url).with_context(|| format!("Invalid {source}"))?;
+    Ok(url.trim_end_matches('/').to_string())
+}
 
-    let client = reqwest::Client::new();
+/// `update.release_url` straight from the config file, or empty.
+///
+/// Deliberately not `load_config`: updating must still work when the config
+/// is from a newer version or its keys can't be resolved, and must never
+/// rewrite it. An unreadable file means the default URL.
+fn configured_release_url() -> String {
+    config_path()
+        .and_then(|path| Ok(fs::read_to_string(path)?))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|config| Some(config.get("update")?.get("release_url")?.as_str()?.to_string()))
+        .unwrap_or_default()
+}
+
+/// GET a JSON document from the releases API; `None` when it doesn't exist
+async fn get_json<T: DeserializeOwned>(client: &reqwest::Client, url: &str) -> Result<Option<T>> {
     let response = client
-        .get(GITHUB_RELEASES_URL)
+        .get(url)
         .header("User-Agent", "mahoraga-updater")
         .send()
         .await
-        .context("Failed to fetch latest release from GitHub")?;
+        .with_context(|| format!("Failed to fetch release data from {url}"))?;
 
     if response.status() == reqwest::StatusCode::NOT_FOUND {
-        println!("No releases found on GitHub.");
-        return Ok(());
+        return Ok(None);
     }
 
     if !response.status().is_success() {
         bail!(
-            "GitHub API returned status {}: {}",
+            "Release server returned status {}: {}",
             response.status(),
             response.text().await.unwrap_or_default()
         );
     }
 
-    let release: Release = response
+    let value = response
         .json()
         .await
-        .context("Failed to parse release data from GitHub")?;
+        .with_context(|| format!("Failed to parse release data from {url}"))?;
+    Ok(Some(value))
+}
 
-    let current = parse_version(current_version)?;
-    let latest = parse_version(&release.tag_name)?;
+/// Newest release on the given channel
+async fn latest_release(client: &reqwest::Client, base: &str, channel: Channel) -> Result<Option<Release>> {
+    match channel {
+        // GitHub's "latest" already skips drafts and pre-releases
+        Channel::Stable => get_json(client, &format!("{base}/latest")).await,
+        Channel::Beta => {
+            let releases: Vec<Release> =
+                get_json(client, &format!("{base}?per_page=100"))
+                    .await?
+                    .unwrap_or_default();
+            Ok(releases
+                .into_iter()
+                .filter(|release| !release.draft)
+                .filter_map(|release| Some((parse_version(&release.tag_name).ok()?, release)))
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, release)| release))
+        }
+    }
+}
+
+/// Result of the last background update check
+#[derive(Default, Serialize, Deserialize)]
+struct UpdateCheck {
+    /// Unix time of the last check, successful or not
+    checked_at: u64,
+    /// Newest stable release tag seen, empty if none
+    #[serde(default)]
+    latest: String,
+}
+
+/// The cached tag if it is newer than this build
+fn newer_release(latest: &str) -> Option<String> {
+    let current = parse_version(env!("CARGO_PKG_VERSION")).ok()?;
+    let latest_version = parse_version(latest).ok()?;
+    (latest_version > current).then(|| latest.to_string())
+}
+
+/// Look for a newer stable release, at most once per day.
+///
+/// Used by the TUI at startup. The result is cached in the config dir so
+/// later launches show the notice without touching the network.
+pub async fn check_for_update() -> Result<Option<String>> {
+    let path = config_dir()?.join(UPDATE_CHECK_FILE);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
-    if latest <= current {
-        println!("Already up to date (v{current_version}).");
-        return Ok(());
+    // File reads and writes stay off the async runtime's threads
+    let (mut check, base) = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || {
+            let check: UpdateCheck = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| toml::from_str(&content).ok())
+                .unwrap_or_default();
+            (check, releases_url())
+        }
+    })
+    .await?;
+
+    if now.saturating_sub(check.checked_at) < UPDATE_CHECK_INTERVAL.as_secs() {
+        return Ok(newer_release(&check.latest));
+    }
+
+    // Record the attempt first, so an unreachable server is retried tomorrow
+    // rather than on every launch
+    check.checked_at = now;
+    let client = reqwest::Client::builder()
+        .timeout(UPDATE_CHECK_TIMEOUT)
+        .build()
+        .context("Failed to create HTTP client")?;
+    let result = match base {
+        Ok(base) => latest_release(&client, &base, Channel::Stable).await,
+        Err(err) => Err(err),
+    };
+    if let Ok(release) = &result {
+        check.latest = release
+            .as_ref()
+            .map(|release| release.tag_name.clone())
+            .unwrap_or_default();
+    }
+
+    let latest = check.latest.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        fs::create_dir_all(path.parent().context("Invalid update check path")?)
+            .context("Failed to create config directory")?;
+        fs::write(&path, toml::to_string(&check)?).context("Failed to save update check")
+    })
+    .await??;
+
+    result?;
+    Ok(newer_release(&latest))
+}
+
+/// Release the flags point at: the requested tag, or the newest on the
+/// channel. `None` when the server has no releases.
+async fn select_release(client: &reqwest::Client, base: &str, args: &UpdateArgs) -> Result<Option<Release>> {
+    match &args.version {
+        Some(requested) => {
+            // Accept both `1.2.0` and `v1.2.0`; release tags carry the `v`
+            parse_version(requested)?;
+            let tag = format!("v{}", requested.strip_prefix('v').unwrap_or(requested));
+            let release = get_json(client, &format!("{base}/tags/{tag}"))
+                .await?
+                .with_context(|| format!("Release {tag} not found"))?;
+            Ok(Some(release))
+        }
+        None => latest_release(client, base, args.channel).await,
     }
+}
 
-    println!("New version available: {}", release.tag_name);
+/// Whether installing a release would change this build. A pinned version
+/// may go backwards; a channel only moves forward.
+fn is_update(target: &Version, current: &Version, pinned: bool) -> bool {
+    if pinned {
+        target != current
+    } else {
+        target > current
+    }
+}
 
+/// Archive, checksum and signature for this platform
+fn platform_assets(release: &Release) -> Result<(&Asset, &Asset, &Asset)> {
     let platform = platform_target()?;
     let expected_name = format!("mahoraga-{}-{platform}.tar.gz", release.tag_name);
 
@@ -102,16 +555,105 @@ pub async fn run_update() -> Result<()> {
                 release.assets.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", "))
         })?;
 
+    let checksum_asset = find_asset(release, &format!("{expected_name}.sha256"))?;
+    let signature_asset = find_asset(release, &format!("{expected_name}.minisig"))?;
+    Ok((asset, checksum_asset, signature_asset))
+}
+
+/// Print whether an update is available as a single JSON object
+async fn check(args: &UpdateArgs) -> Result<ExitCode> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    let current = parse_version(current_version)?;
+    let client = reqwest::Client::new();
+    let base = releases_url()?;
+
+    let release = select_release(&client, &base, args).await?;
+    let update = match &release {
+        Some(release) => is_update(&parse_version(&release.tag_name)?, &current, args.version.is_some()),
+        None => false,
+    };
+    // An update without an installable asset for this platform is still
+    // reported, with `asset: null`
+    let asset = match &release {
+        Some(release) if update => platform_assets(release).ok().map(|(asset, _, _)| asset),
+        _ => None,
+    };
+
+    let report = serde_json::json!({
+        "current_version": format!("v{current_version}"),
+        "latest_version": release.as_ref().map(|release| &release.tag_name),
+        "update_available": update,
+        "asset": asset.map(|asset| &asset.name),
+        "url": asset.map(|asset| &asset.browser_download_url),
+    });
+    println!("{report}");
+
+    Ok(if update {
+        ExitCode::from(EXIT_UPDATE_AVAILABLE)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+pub async fn run_update(args: UpdateArgs) -> Result<ExitCode> {
+    if args.rollback {
+        rollback()?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.check {
+        return check(&args).await;
+    }
+
+    let current_version = env!("CARGO_PKG_VERSION");
+    println!("Current version: v{current_version}");
+    println!("Checking for updates...");
+
+    let client = reqwest::Client::new();
+    let base = releases_url()?;
+    let current = parse_version(current_version)?;
+
+    let Some(release) = select_release(&client, &base, &args).await? else {
+        println!("No releases found.");
+        return Ok(ExitCode::SUCCESS);
+    };
+    let target = parse_version(&release.tag_name)?;
+    let pinned = args.version.is_some();
+
+    if !is_update(&target, &current, pinned) {
+        if pinned {
+            println!("Already on {}.", release.tag_name);
+        } else {
+            println!("Already up to date (v{current_version}).");
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if pinned && target < current {
+        println!("Downgrading to {}", release.tag_name);
+    } else if pinned {
+        println!("Upgrading to {}", release.tag_name);
+    } else if target.is_prerelease() || release.prerelease {
+        println!("New pre-release available: {}", release.tag_name);
+    } else {
+        println!("New version available: {}", release.tag_name);
+    }
+
+    let (asset, checksum_asset, signature_asset) = platform_assets(&release)?;
+
     println!("Downloading {}...", asset.name);
-    let archive_bytes = client
-        .get(&asset.browser_download_url)
-        .header("User-Agent", "mahoraga-updater")
-        .send()
-        .await
-        .context("Failed to download release asset")?
-        .bytes()
-        .await
-        .context("Failed to read release asset bytes")?;
+    let archive_bytes = download(&client, asset).await?;
+    let checksum = download(&client, checksum_asset).await?;
+    let signature = download(&client, signature_asset).await?;
+
+    println!("Verifying checksum and signature...");
+    verify_checksum(&archive_bytes, &String::from_utf8_lossy(&checksum), &asset.name)?;
+    verify_signature(
+        &archive_bytes,
+        &String::from_utf8_lossy(&signature),
+        RELEASE_PUBLIC_KEY,
+        &asset.name,
+        &release.tag_name,
+    )?;
 
     println!("Extracting binary...");
     let decoder = GzDecoder::new(&archive_bytes[..]);
@@ -134,26 +676,131 @@ pub async fn run_update() -> Result<()> {
 
     let new_binary = new_binary.context("Binary 'mahoraga' not found in the archive")?;
 
-    let current_exe = std::env::current_exe().context("Failed to determine current executable path")?;
-    let exe_dir = current_exe
-        .parent()
-        .context("Failed to determine executable directory")?;
-    let tmp_path = exe_dir.join(".mahoraga_update_tmp");
+    let (current_exe, backup) = exe_paths()?;
+
+    if args.dry_run {
+        // Staged outside the install dir, which is left alone
+        let dir = private_temp_dir()?;
+        let staged = dir.join("mahoraga");
+        let result = write_new_executable(&staged, &new_binary)
+            .and_then(|()| smoke_test(&staged, &release.tag_name).context("The new binary failed its self-check"));
+        fs::remove_dir_all(&dir).context("Failed to remove temporary update directory")?;
+        result?;
+        println!(
+            "Dry run: {} downloaded and verified. {} was not changed.",
+            release.tag_name,
+            current_exe.display()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
 
+    let tmp_path = backup.with_file_name(".mahoraga_update_tmp");
     fs::write(&tmp_path, &new_binary).context("Failed to write temporary update file")?;
 
     #[cfg(unix)]
     fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o755))
         .context("Failed to set executable permissions")?;
 
-    fs::rename(&tmp_path, &current_exe).context(
-        "Failed to replace binary. You may need to run with elevated permissions (e.g., sudo).",
-    )?;
+    // Keep the running binary so the update can be undone
+    fs::copy(&current_exe, &backup).with_context(|| {
+        format!("Failed to back up the current binary to {}", backup.display())
+    })?;
+
+    replace_binary(&tmp_path, &current_exe)?;
+
+    if let Err(err) = smoke_test(&current_exe, &release.tag_name) {
+        fs::copy(&backup, &tmp_path).context("Failed to restore the previous binary")?;
+        replace_binary(&tmp_path, &current_exe)?;
+        bail!("The new binary failed its self-check, so the update was rolled back: {err:#}");
+    }
 
     println!(
         "Successfully updated to {} !",
         release.tag_name
     );
+    println!("Run `mahoraga update --rollback` to return to v{current_version}.");
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_checksum() {
+        let sha = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_checksum(b"hello", &format!("{sha}  mahoraga.tar.gz\n"), "mahoraga.tar.gz").is_ok());
+        assert!(verify_checksum(b"hello", &sha.to_uppercase(), "mahoraga.tar.gz").is_ok());
+        assert!(verify_checksum(b"hellO", sha, "mahoraga.tar.gz").is_err());
+        assert!(verify_checksum(b"hello", "", "mahoraga.tar.gz").is_err());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let v = |s: &str| parse_version(s).unwrap();
+        assert_eq!(v("v1.2.3"), Version { major: 1, minor: 2, patch: 3, pre: vec![] });
+        assert_eq!(v("1.2.3+build.5"), v("v1.2.3"));
+        assert_eq!(v("v1.0.0-beta.2+sha.abc").to_string(), "v1.0.0-beta.2");
+
+        // Precedence example from the semver spec
+        let ordered = [
+            "1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta",
+            "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        for bad in ["1.2", "1.2.3.4", "01.2.3", "1.2.3-", "1.2.3-beta..1", "1.2.3+", "1.2.x"] {
+            assert!(parse_version(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_is_update() {
+        let v = |s: &str| parse_version(s).unwrap();
+        assert!(is_update(&v("v1.1.0"), &v("v1.0.0"), false));
+        assert!(!is_update(&v("v0.9.0"), &v("v1.0.0"), false));
+        assert!(is_update(&v("v0.9.0"), &v("v1.0.0"), true));
+        assert!(!is_update(&v("v1.0.0"), &v("v1.0.0"), true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_smoke_test() {
+        let dir = std::env::temp_dir().join(format!("mahoraga-update-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("mahoraga");
+
+        fs::write(&exe, "#!/bin/sh\necho mahoraga 1.2.3\n").unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(smoke_test(&exe, "v1.2.3").is_ok());
+        assert!(smoke_test(&exe, "v1.2.4").is_err());
+
+        fs::write(&exe, "#!/bin/sh\nexit 1\n").unwrap();
+        assert!(smoke_test(&exe, "v1.2.3").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let public_key = "RWS+zMkz31Wp81VyC3Qg9h6FAHqubTUM/c2DgG3ZWewXyqGnfAKijnPF";
+        let signature = "untrusted comment: signature from rsign secret key
+RUS+zMkz31Wp82mQlFbwV/QFFL0K079NeaXFPEGXKXkdmOILw7k9lybYOK+d8VvADj10Bmcc2DosyIJihL06nHHxCJVFTXCeDg0=
+trusted comment: file:mahoraga.tar.gz version:v0.2.0
+hMZQf2uQzCHiCbkbidPmnIx35dhO9FzzBsk+YCPOR+XgDBEorenon0pfUPX0uWPr+tDHJ2K1YBV5V4KvhJ63Bw==
+";
+        let verify = |archive: &[u8], signature, public_key, name, version| {
+            verify_signature(archive, signature, public_key, name, version)
+        };
+        assert!(verify(b"hello", signature, public_key, "mahoraga.tar.gz", "v0.2.0").is_ok());
+        assert!(verify(b"hellO", signature, public_key, "mahoraga.tar.gz", "v0.2.0").is_err());
+        assert!(verify(b"hello", signature, RELEASE_PUBLIC_KEY, "mahoraga.tar.gz", "v0.2.0").is_err());
+        assert!(verify(b"hello", "garbage", public_key, "mahoraga.tar.gz", "v0.2.0").is_err());
+        // A validly signed archive served under another name or release
+        assert!(verify(b"hello", signature, public_key, "mahoraga-new.tar.gz", "v0.2.0").is_err());
+        assert!(verify(b"hello", signature, public_key, "mahoraga.tar.gz", "v0.3.0").is_err());
+    }
 }
@@ -1,8 +1,13 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
 
-use crate::types::Config;
+use crate::migrations::{migrate, CONFIG_VERSION};
+use crate::secrets::{delete_orphaned_secrets, externalize_secrets, key_source, resolve_secrets, KeySource};
+use crate::types::{Config, Profile, ProviderType, SecretStorage};
 
 /// Get the configuration directory path
 pub fn config_dir() -> Result<PathBuf> {
@@ -17,7 +22,91 @@ pub fn config_path() -> Result<PathBuf> {
     Ok(config_dir()?.join("config.toml"))
 }
 
-/// Load configuration from disk, or return default if not found
+/// Write a file atomically, readable only by the owner.
+///
+/// The content goes to a temporary file in the same directory which is then
+/// renamed over `path`, so a crash mid-write never leaves a truncated file.
+/// A symlinked `path`, e.g. from a dotfile manager, is followed so the link
+/// keeps pointing at the updated file.
+pub fn write_private_file(path: &Path, content: &str) -> Result<()> {
+    let target = if path.exists() {
+        fs::canonicalize(path).with_context(|| format!("Failed to resolve {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let file_name = target
+        .file_name()
+        .with_context(|| format!("Invalid file path: {}", path.display()))?
+        .to_string_lossy();
+    let tmp_path = target.with_file_name(format!(".{}.tmp", file_name));
+
+    // A leftover temp file may have looser permissions, so start fresh
+    let _ = fs::remove_file(&tmp_path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let result = options
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, &target));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.with_context(|| format!("Failed to write file: {}", path.display()))?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(dir) = target.parent() {
+        fs::File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .with_context(|| format!("Failed to sync directory: {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Warning if a file holding secrets is readable by other users
+#[cfg(unix)]
+fn permission_warning(path: &Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o077 != 0).then(|| {
+        format!(
+            "{} is accessible by other users (mode {:o}). Run: chmod 600 {}",
+            path.display(),
+            mode,
+            path.display()
+        )
+    })
+}
+
+#[cfg(not(unix))]
+fn permission_warning(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Warnings about config files whose permissions are too open
+pub fn config_permission_warnings() -> Vec<String> {
+    let Ok(dir) = config_dir() else {
+        return Vec::new();
+    };
+    ["config.toml", "secrets.toml"]
+        .iter()
+        .filter_map(|name| permission_warning(&dir.join(name)))
+        .collect()
+}
+
+/// Load configuration from disk, or return default if not found.
+///
+/// Warns on stderr, once per run, if the config files are readable by
+/// other users.
 pub fn load_config() -> Result<Config> {
     let path = config_path()?;
 
@@ -25,11 +114,36 @@ pub fn load_config() -> Result<Config> {
         return Ok(Config::default());
     }
 
+    static PERMISSION_CHECK: Once = Once::new();
+    PERMISSION_CHECK.call_once(|| {
+        for warning in config_permission_warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    });
+
     let content = fs::read_to_string(&path)
         .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-    let config: Config = toml::from_str(&content)
+    let mut table: toml::Table = toml::from_str(&content)
         .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    let from_version = migrate(&mut table)
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+    let mut config: Config = table
+        .try_into()
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+    // Keep the original around in case the upgrade loses something
+    let migrated = from_version < CONFIG_VERSION;
+    if migrated {
+        let backup_path = path.with_file_name(format!("config.toml.v{}.bak", from_version));
+        write_private_file(&backup_path, &content)
+            .with_context(|| format!("Failed to back up config file to {}", backup_path.display()))?;
+    }
+
+    // Move plaintext API keys into the configured secret store
+    if resolve_secrets(&mut config)? || migrated {
+        save_config(&config)?;
+    }
 
     Ok(config)
 }
@@ -41,19 +155,455 @@ pub fn save_config(config: &Config) -> Result<()> {
 
     // Create directory if it doesn't exist
     if !dir.exists() {
-        fs::create_dir_all(&dir)
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&dir)
             .with_context(|| format!("Failed to create config directory: {}", dir.display()))?;
     }
 
-    let content = toml::to_string_pretty(config)
+    // Keys the current file points at, to clean up any the new one drops
+    let previous = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok());
+
+    let stored = externalize_secrets(&file_config(config))?;
+    let content = toml::to_string_pretty(&stored)
         .context("Failed to serialize config")?;
 
-    fs::write(&path, content)
+    write_private_file(&path, &content)
         .with_context(|| format!("Failed to write config file: {}", path.display()))?;
 
+    if let Some(previous) = previous {
+        let current = toml::Table::try_from(&stored).context("Failed to serialize config")?;
+        delete_orphaned_secrets(&previous, &current)?;
+    }
+
+    Ok(())
+}
+
+/// The config as it should be written to disk
+fn file_config(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.version = CONFIG_VERSION;
+    sync_active_profile(&mut config);
+    if let Some((name, setup)) = config.file_profile.take() {
+        apply_profile(&mut config, setup);
+        config.active_profile = name;
+    }
+    config
+}
+
+/// The provider setup currently in use, as a profile
+fn current_profile(config: &Config) -> Profile {
+    Profile {
+        provider: config.provider.clone(),
+        azure: config.azure.clone(),
+        openai: config.openai.clone(),
+        anthropic: config.anthropic.clone(),
+    }
+}
+
+/// Write the setup in use back into the active profile
+pub fn sync_active_profile(config: &mut Config) {
+    if !config.active_profile.is_empty() {
+        let profile = current_profile(config);
+        config.profiles.insert(config.active_profile.clone(), profile);
+    }
+}
+
+/// Switch to the named profile, keeping changes made to the current one
+pub fn switch_profile(config: &mut Config, name: &str) -> Result<()> {
+    let profile = config.profiles.get(name).cloned().with_context(|| {
+        let names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        if names.is_empty() {
+            format!("Unknown profile '{}'. No profiles are defined", name)
+        } else {
+            format!("Unknown profile '{}'. Available: {}", name, names.join(", "))
+        }
+    })?;
+
+    sync_active_profile(config);
+    apply_profile(config, profile);
+    config.active_profile = name.to_string();
+    config.file_profile = None;
+    Ok(())
+}
+
+/// Use the named profile for this run only, as with `--profile`. Saving
+/// writes changes to that profile but keeps the file's active profile.
+pub fn use_profile_for_run(config: &mut Config, name: &str) -> Result<()> {
+    let file_profile = (config.active_profile.clone(), current_profile(config));
+    switch_profile(config, name)?;
+    config.file_profile = Some(file_profile);
+    Ok(())
+}
+
+/// Make a profile's setup the one in use
+fn apply_profile(config: &mut Config, profile: Profile) {
+    config.provider = profile.provider;
+    config.azure = profile.azure;
+    config.openai = profile.openai;
+    config.anthropic = profile.anthropic;
+}
+
+/// Save the setup in use as a new profile and make it active
+pub fn create_profile(config: &mut Config, name: &str) {
+    config.file_profile = None;
+    sync_active_profile(config);
+    config.active_profile = name.to_string();
+    sync_active_profile(config);
+}
+
+/// Name of the profile after the active one, wrapping around
+pub fn next_profile(config: &Config) -> Option<String> {
+    let mut names = config.profiles.keys();
+    names
+        .clone()
+        .find(|name| **name > config.active_profile)
+        .or_else(|| names.next())
+        .cloned()
+}
+
+/// Whether a provider has everything it needs to run an analysis
+pub fn provider_configured(config: &Config, provider: ProviderType) -> bool {
+    // An explicit env var only counts once it holds a key
+    let has_key = match key_source(config, provider) {
+        KeySource::Env(var) => env::var(var).is_ok_and(|key| !key.is_empty()),
+        KeySource::Missing => false,
+        _ => true,
+    };
+    match provider {
+        ProviderType::Azure => has_key && !config.azure.url.is_empty() && !config.azure.deployment.is_empty(),
+        ProviderType::OpenAI => has_key && !config.openai.model.is_empty(),
+        ProviderType::Anthropic => has_key && !config.anthropic.model.is_empty(),
+    }
+}
+
+/// Keys that can be read and written with `mahoraga config`, in listing order
+pub const CONFIG_KEYS: &[&str] = &[
+    "provider.active",
+    "azure.url",
+    "azure.api_key",
+    "azure.api_key_env",
+    "azure.api_key_cmd",
+    "azure.deployment",
+    "azure.api_version",
+    "openai.api_key",
+    "openai.api_key_env",
+    "openai.api_key_cmd",
+    "openai.model",
+    "anthropic.api_key",
+    "anthropic.api_key_env",
+    "anthropic.api_key_cmd",
+    "anthropic.model",
+    "analysis.rubric",
+    "analysis.min_score",
+    "secrets.storage",
+    "update.release_url",
+];
+
+/// Whether a config key holds an API key
+pub fn is_secret_key(key: &str) -> bool {
+    key.ends_with(".api_key")
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow::anyhow!("Unknown config key '{}'. Run `mahoraga config list` to see all keys", key)
+}
+
+/// The identifier serde uses for an enum value, e.g. `openai`
+fn enum_name<T: serde::Serialize>(value: &T) -> String {
+    toml::Value::try_from(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Parse an enum value from its serde identifier
+fn parse_enum<T: serde::de::DeserializeOwned>(key: &str, value: &str, expected: &str) -> Result<T> {
+    toml::Value::String(value.to_string())
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid value '{}' for {}. Expected one of: {}", value, key, expected))
+}
+
+/// Read a config value by its dotted key, e.g. `openai.model`
+pub fn get_config_value(config: &Config, key: &str) -> Result<String> {
+    let value = match key {
+        "provider.active" => enum_name(&config.provider.active),
+        "azure.url" => config.azure.url.clone(),
+        "azure.api_key" => config.azure.api_key.clone(),
+        "azure.api_key_env" => config.azure.api_key_env.clone(),
+        "azure.api_key_cmd" => config.azure.api_key_cmd.clone(),
+        "azure.deployment" => config.azure.deployment.clone(),
+        "azure.api_version" => config.azure.api_version.clone(),
+        "openai.api_key" => config.openai.api_key.clone(),
+        "openai.api_key_env" => config.openai.api_key_env.clone(),
+        "openai.api_key_cmd" => config.openai.api_key_cmd.clone(),
+        "openai.model" => config.openai.model.clone(),
+        "anthropic.api_key" => config.anthropic.api_key.clone(),
+        "anthropic.api_key_env" => config.anthropic.api_key_env.clone(),
+        "anthropic.api_key_cmd" => config.anthropic.api_key_cmd.clone(),
+        "anthropic.model" => config.anthropic.model.clone(),
+        "analysis.rubric" => config.analysis.rubric.clone(),
+        "analysis.min_score" => config
+            .analysis
+            .min_score
+            .map(|score| score.to_string())
+            .unwrap_or_default(),
+        "secrets.storage" => enum_name(&config.secrets.storage),
+        "update.release_url" => config.update.release_url.clone(),
+        _ => return Err(unknown_key(key)),
+    };
+    Ok(value)
+}
+
+/// Whether a value looks like an Azure API version, e.g. `2024-02-15-preview`
+fn is_api_version(value: &str) -> bool {
+    let (Some(date), Some(suffix)) = (value.get(..10), value.get(10..)) else {
+        return false;
+    };
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    let number = |part: &str, len: usize| -> Option<u32> {
+        if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse().ok()
+        } else {
+            None
+        }
+    };
+    let valid_date = number(year, 4).is_some()
+        && number(month, 2).is_some_and(|month| (1..=12).contains(&month))
+        && number(day, 2).is_some_and(|day| (1..=31).contains(&day));
+    let valid_suffix = suffix.is_empty()
+        || (suffix.len() > 1 && suffix.starts_with('-') && suffix[1..].bytes().all(|b| b.is_ascii_alphanumeric()));
+    valid_date && valid_suffix
+}
+
+/// Check an API key's shape; empty keys are allowed since the key may come
+/// from an environment variable or command
+fn check_api_key(provider: ProviderType, key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Ok(());
+    }
+    if key.chars().any(char::is_whitespace) {
+        bail!("API key must not contain spaces");
+    }
+    match provider {
+        // Azure key formats vary between resource kinds, so any key without
+        // whitespace is accepted
+        ProviderType::Azure => {}
+        ProviderType::OpenAI => {
+            if !key.starts_with("sk-") || key.len() < 20 {
+                bail!("OpenAI keys start with sk- and are at least 20 characters");
+            }
+        }
+        ProviderType::Anthropic => {
+            if !key.starts_with("sk-ant-") || key.len() < 20 {
+                bail!("Anthropic keys start with sk-ant- and are at least 20 characters");
+            }
+        }
+    }
     Ok(())
 }
 
+/// Check a value for a dotted key before it is stored.
+///
+/// The settings screen and `mahoraga config set` both use this, so they
+/// accept the same input.
+pub fn validate_config_value(key: &str, value: &str) -> Result<()> {
+    match key {
+        "azure.url" if !value.is_empty() => {
+            let url = reqwest::Url::parse(value).map_err(|_| anyhow::anyhow!("Not a valid URL"))?;
+            if url.scheme() != "https" || url.host_str().is_none() {
+                bail!("Must be an https:// URL");
+            }
+        }
+        "update.release_url" if !value.is_empty() => {
+            // Plain http is fine here: mirrors are often internal, and the
+            // archives are signature-checked regardless
+            let url = reqwest::Url::parse(value).map_err(|_| anyhow::anyhow!("Not a valid URL"))?;
+            if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+                bail!("Must be an http:// or https:// URL");
+            }
+        }
+        "azure.api_version" if !is_api_version(value) => {
+            bail!("Expected a date like 2024-02-15 or 2024-02-15-preview");
+        }
+        "azure.api_key" => check_api_key(ProviderType::Azure, value)?,
+        "openai.api_key" => check_api_key(ProviderType::OpenAI, value)?,
+        "anthropic.api_key" => check_api_key(ProviderType::Anthropic, value)?,
+        "openai.model" | "anthropic.model" if value.trim().is_empty() => {
+            bail!("Model must not be empty");
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Validate and set a config value by its dotted key
+pub fn set_config_value(config: &mut Config, key: &str, value: &str) -> Result<()> {
+    validate_config_value(key, value).with_context(|| format!("Invalid value for {}", key))?;
+    set_config_value_unchecked(config, key, value)
+}
+
+/// Set a config value by its dotted key, only checking that it parses.
+///
+/// The settings screen stores values as typed and shows problems on the
+/// field instead, so they can be fixed before saving.
+pub fn set_config_value_unchecked(config: &mut Config, key: &str, value: &str) -> Result<()> {
+    let field = match key {
+        "provider.active" => {
+            config.provider.active = parse_enum::<ProviderType>(key, value, "azure, openai, anthropic")?;
+            return Ok(());
+        }
+        "analysis.min_score" => {
+            config.analysis.min_score = if value.is_empty() {
+                None
+            } else {
+                match value.parse::<u8>() {
+                    Ok(score) if score <= 100 => Some(score),
+                    _ => bail!("Invalid value '{}' for {}. Expected a score from 0 to 100", value, key),
+                }
+            };
+            return Ok(());
+        }
+        "secrets.storage" => {
+            config.secrets.storage = parse_enum::<SecretStorage>(key, value, "plaintext, keyring, file")?;
+            return Ok(());
+        }
+        "azure.url" => &mut config.azure.url,
+        "azure.api_key" => &mut config.azure.api_key,
+        "azure.api_key_env" => &mut config.azure.api_key_env,
+        "azure.api_key_cmd" => &mut config.azure.api_key_cmd,
+        "azure.deployment" => &mut config.azure.deployment,
+        "azure.api_version" => &mut config.azure.api_version,
+        "openai.api_key" => &mut config.openai.api_key,
+        "openai.api_key_env" => &mut config.openai.api_key_env,
+        "openai.api_key_cmd" => &mut config.openai.api_key_cmd,
+        "openai.model" => &mut config.openai.model,
+        "anthropic.api_key" => &mut config.anthropic.api_key,
+        "anthropic.api_key_env" => &mut config.anthropic.api_key_env,
+        "anthropic.api_key_cmd" => &mut config.anthropic.api_key_cmd,
+        "anthropic.model" => &mut config.anthropic.model,
+        "analysis.rubric" => &mut config.analysis.rubric,
+        "update.release_url" => &mut config.update.release_url,
+        _ => return Err(unknown_key(key)),
+    };
+    *field = value.to_string();
+    Ok(())
+}
+
+/// Name of the project-local config file
+pub const PROJECT_CONFIG_FILE: &str = ".mahoraga.toml";
+
+/// Keys a project config may set, by section.
+///
+/// API keys, key commands and the Azure URL stay out of this list: a
+/// checked-out repository must not be able to run commands or send the
+/// user's key to another endpoint.
+const PROJECT_KEYS: &[(&str, &[&str])] = &[
+    ("provider", &["active"]),
+    ("azure", &["deployment", "api_version"]),
+    ("openai", &["model"]),
+    ("anthropic", &["model"]),
+    ("analysis", &["rubric", "min_score"]),
+];
+
+/// Overrides from a `.mahoraga.toml` in the project being worked on
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    overrides: toml::Table,
+}
+
+impl ProjectConfig {
+    /// Parse a project config, rejecting keys it isn't allowed to set
+    fn parse(path: PathBuf, content: &str) -> Result<Self> {
+        let overrides: toml::Table = toml::from_str(content)
+            .with_context(|| format!("Failed to parse project config: {}", path.display()))?;
+
+        for (section, value) in &overrides {
+            let Some((_, allowed)) = PROJECT_KEYS.iter().find(|(name, _)| name == section) else {
+                bail!("{}: `[{}]` can't be set in a project config", path.display(), section);
+            };
+            let Some(table) = value.as_table() else {
+                bail!("{}: `{}` must be a table", path.display(), section);
+            };
+            if let Some(key) = table.keys().find(|key| !allowed.contains(&key.as_str())) {
+                bail!(
+                    "{}: `{}.{}` can't be set in a project config. \
+                     Keep API keys and endpoints in the global config",
+                    path.display(),
+                    section,
+                    key
+                );
+            }
+            // Same rules as `mahoraga config set`
+            for (key, value) in table {
+                let key = format!("{}.{}", section, key);
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                set_config_value(&mut Config::default(), &key, &value)
+                    .with_context(|| format!("{}: invalid `{}`", path.display(), key))?;
+            }
+        }
+
+        Ok(Self { path, overrides })
+    }
+
+    /// Whether this project config sets the given dotted key
+    pub fn overrides_key(&self, key: &str) -> bool {
+        key.split_once('.').is_some_and(|(section, name)| {
+            self.overrides
+                .get(section)
+                .and_then(toml::Value::as_table)
+                .is_some_and(|table| table.contains_key(name))
+        })
+    }
+
+    /// The global config with this project's settings merged over it
+    pub fn apply(&self, config: &Config) -> Result<Config> {
+        let mut merged = toml::Table::try_from(config).context("Failed to serialize config")?;
+        for (section, value) in &self.overrides {
+            match (merged.get_mut(section), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(table)) => {
+                    base.extend(table.clone());
+                }
+                _ => {
+                    merged.insert(section.clone(), value.clone());
+                }
+            }
+        }
+        merged
+            .try_into()
+            .with_context(|| format!("Invalid project config: {}", self.path.display()))
+    }
+}
+
+/// Find and load the nearest `.mahoraga.toml` in the current directory or
+/// one of its parents
+pub fn load_project_config() -> Result<Option<ProjectConfig>> {
+    let cwd = env::current_dir().context("Could not determine current directory")?;
+    let Some(path) = cwd
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read project config: {}", path.display()))?;
+    ProjectConfig::parse(path, &content).map(Some)
+}
+
 /// Reset configuration to defaults
 pub fn reset_config() -> Result<Config> {
     let config = Config::default();
@@ -70,4 +620,163 @@ mod tests {
         let config = Config::default();
         assert_eq!(config.provider.active.as_str(), "azure");
     }
+
+    #[test]
+    fn test_switch_profile() {
+        let mut config = Config::default();
+        config.openai.model = "gpt-4o".to_string();
+        create_profile(&mut config, "personal");
+
+        create_profile(&mut config, "work");
+        config.provider.active = ProviderType::Anthropic;
+        assert_eq!(next_profile(&config).as_deref(), Some("personal"));
+
+        switch_profile(&mut config, "personal").unwrap();
+        assert_eq!(config.provider.active, ProviderType::Azure);
+        assert_eq!(config.openai.model, "gpt-4o");
+        assert_eq!(next_profile(&config).as_deref(), Some("work"));
+
+        // Changes to the active setup are kept when switching away
+        config.openai.model = "gpt-4o-mini".to_string();
+        switch_profile(&mut config, "work").unwrap();
+        assert_eq!(config.provider.active, ProviderType::Anthropic);
+        assert_eq!(config.profiles["personal"].openai.model, "gpt-4o-mini");
+
+        assert!(switch_profile(&mut config, "missing").is_err());
+    }
+
+    #[test]
+    fn test_profile_for_run_is_not_saved() {
+        let mut config = Config::default();
+        config.openai.model = "gpt-4o".to_string();
+        create_profile(&mut config, "work");
+        config.active_profile.clear();
+
+        use_profile_for_run(&mut config, "work").unwrap();
+        config.openai.model = "gpt-4o-mini".to_string();
+
+        // Edits go to the profile, but the file keeps its own active setup
+        let stored = file_config(&config);
+        assert_eq!(stored.active_profile, "");
+        assert_eq!(stored.openai.model, "gpt-4o");
+        assert_eq!(stored.profiles["work"].openai.model, "gpt-4o-mini");
+
+        // An explicit switch during the run does persist
+        switch_profile(&mut config, "work").unwrap();
+        assert_eq!(file_config(&config).active_profile, "work");
+    }
+
+    #[test]
+    fn test_config_values() {
+        let mut config = Config::default();
+        for key in CONFIG_KEYS {
+            assert!(get_config_value(&config, key).is_ok(), "{}", key);
+        }
+
+        set_config_value(&mut config, "provider.active", "anthropic").unwrap();
+        assert_eq!(config.provider.active, ProviderType::Anthropic);
+        set_config_value(&mut config, "analysis.min_score", "70").unwrap();
+        assert_eq!(get_config_value(&config, "analysis.min_score").unwrap(), "70");
+
+        assert!(set_config_value(&mut config, "provider.active", "gemini").is_err());
+        assert!(set_config_value(&mut config, "analysis.min_score", "101").is_err());
+        assert!(set_config_value(&mut config, "openai.temperature", "1").is_err());
+    }
+
+    #[test]
+    fn test_validate_config_value() {
+        assert!(validate_config_value("azure.url", "https://example.openai.azure.com").is_ok());
+        assert!(validate_config_value("azure.url", "http://example.openai.azure.com").is_err());
+        assert!(validate_config_value("azure.url", "example.openai.azure.com").is_err());
+
+        assert!(validate_config_value("azure.api_version", "2024-02-15-preview").is_ok());
+        assert!(validate_config_value("azure.api_version", "2024-06-01").is_ok());
+        assert!(validate_config_value("azure.api_version", "2024-13-01").is_err());
+        assert!(validate_config_value("azure.api_version", "latest").is_err());
+
+        assert!(validate_config_value("openai.api_key", "").is_ok());
+        assert!(validate_config_value("openai.api_key", "sk-proj-abcdefghijklmnop").is_ok());
+        assert!(validate_config_value("anthropic.api_key", "sk-proj-abcdefghijklmnop").is_err());
+        assert!(validate_config_value("azure.api_key", &"a".repeat(32)).is_ok());
+        assert!(validate_config_value("azure.api_key", "key-from-a-newer-resource").is_ok());
+        assert!(validate_config_value("azure.api_key", "abc def").is_err());
+
+        assert!(validate_config_value("openai.model", " ").is_err());
+
+        assert!(validate_config_value("update.release_url", "").is_ok());
+        assert!(validate_config_value("update.release_url", "http://mirror.internal/releases").is_ok());
+        assert!(validate_config_value("update.release_url", "ftp://mirror.internal/releases").is_err());
+    }
+
+    #[test]
+    fn test_project_config_overrides() {
+        let project = ProjectConfig::parse(
+            PathBuf::from(PROJECT_CONFIG_FILE),
+            "[openai]\nmodel = \"gpt-4o\"\n\n[analysis]\nmin_score = 70\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.openai.api_key = "sk-global".to_string();
+        let merged = project.apply(&config).unwrap();
+        assert_eq!(merged.openai.model, "gpt-4o");
+        assert_eq!(merged.openai.api_key, "sk-global");
+        assert_eq!(merged.analysis.min_score, Some(70));
+        assert!(project.overrides_key("openai.model"));
+        assert!(!project.overrides_key("anthropic.model"));
+
+        for content in [
+            "[openai]\napi_key = \"sk\"",
+            "[azure]\nurl = \"https://x\"",
+            "[secrets]\n",
+            "[analysis]\nmin_score = 150",
+            "[azure]\napi_version = \"latest\"",
+            "[provider]\nactive = \"gemini\"",
+        ] {
+            assert!(ProjectConfig::parse(PathBuf::from(PROJECT_CONFIG_FILE), content).is_err());
+        }
+    }
+
+    #[test]
+    fn test_provider_configured_needs_env_key() {
+        let mut config = Config::default();
+        config.openai.api_key_env = "MAHORAGA_TEST_CONFIGURED_KEY".to_string();
+        assert!(!provider_configured(&config, ProviderType::OpenAI));
+
+        env::set_var("MAHORAGA_TEST_CONFIGURED_KEY", "");
+        assert!(!provider_configured(&config, ProviderType::OpenAI));
+
+        env::set_var("MAHORAGA_TEST_CONFIGURED_KEY", "sk-test");
+        assert!(provider_configured(&config, ProviderType::OpenAI));
+        env::remove_var("MAHORAGA_TEST_CONFIGURED_KEY");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("mahoraga-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(permission_warning(&path).is_some());
+
+        write_private_file(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(permission_warning(&path).is_none());
+        assert!(!dir.join(".config.toml.tmp").exists());
+
+        // A symlinked config stays a symlink, and its target gets the content
+        let link = dir.join("linked.toml");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        write_private_file(&link, "via link").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "via link");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
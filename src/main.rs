@@ -1,15 +1,21 @@
 mod app;
+mod clipboard;
 mod commands;
 mod config;
+mod migrations;
 mod providers;
+mod secrets;
 mod types;
 mod ui;
 
 use std::io;
+use std::process::ExitCode;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use crossterm::{
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    cursor::Show,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -25,6 +31,10 @@ use crate::app::App;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Use the named configuration profile for this run
+    #[arg(long, global = true)]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -32,46 +42,71 @@ enum Commands {
     /// Launch the TUI application
     Summon,
     /// Update mahoraga to the latest version
-    Update,
+    Update(commands::UpdateArgs),
     /// Uninstall mahoraga (removes binary and config)
-    Uninstall,
+    Uninstall(commands::UninstallArgs),
+    /// Read and write configuration values
+    Config {
+        #[command(subcommand)]
+        action: commands::ConfigAction,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
 
+    if cli.profile.is_some() && matches!(cli.command, Some(Commands::Update(_) | Commands::Uninstall(_))) {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--profile only applies to the TUI and `config` commands")
+            .exit();
+    }
+
     match cli.command {
         Some(Commands::Summon) | None => {
-            run_tui().await?;
+            run_tui(cli.profile.as_deref()).await?;
+        }
+        Some(Commands::Update(args)) => {
+            return commands::run_update(args).await;
         }
-        Some(Commands::Update) => {
-            commands::run_update().await?;
+        Some(Commands::Uninstall(args)) => {
+            commands::run_uninstall(args)?;
         }
-        Some(Commands::Uninstall) => {
-            commands::run_uninstall()?;
+        Some(Commands::Config { action }) => {
+            commands::run_config(action, cli.profile.as_deref())?;
         }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-async fn run_tui() -> Result<()> {
+async fn run_tui(profile: Option<&str>) -> Result<()> {
+    // Create the app first, so config errors print to a normal terminal
+    let mut app = App::new(profile)?;
+
     // Setup terminal
+    let _guard = TerminalGuard;
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create and run app
-    let mut app = App::new()?;
-    let result = app.run(&mut terminal).await;
+    app.run(&mut terminal).await
+}
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+/// Restores the terminal when dropped, on every exit path including errors
+struct TerminalGuard;
 
-    result
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            DisableMouseCapture,
+            DisableBracketedPaste,
+            LeaveAlternateScreen,
+            Show
+        );
+    }
 }
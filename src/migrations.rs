@@ -0,0 +1,96 @@
+use anyhow::{bail, Context, Result};
+use toml::{Table, Value};
+
+/// Current config schema version, stored as `version` in config.toml
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrade steps between schema versions; entry `n` upgrades version `n`
+/// to `n + 1`. Migrations work on the raw TOML so they can handle layouts
+/// the current `Config` no longer deserializes.
+const MIGRATIONS: &[fn(&mut Table)] = &[drop_empty_defaults];
+
+/// Provider sections and the keys that have serde defaults
+const DEFAULTED_KEYS: &[(&str, &str)] = &[
+    ("azure", "api_version"),
+    ("openai", "model"),
+    ("anthropic", "model"),
+];
+
+/// Version of a parsed config file; files from before versioning are 0
+fn config_version(table: &Table) -> Result<u32> {
+    match table.get("version") {
+        None => Ok(0),
+        Some(Value::Integer(version)) => {
+            u32::try_from(*version).with_context(|| format!("Invalid config version: {}", version))
+        }
+        Some(value) => bail!("Invalid config version: {}", value),
+    }
+}
+
+/// Upgrade a parsed config file to the current schema version.
+///
+/// Returns the version the file was at before migrating.
+pub fn migrate(table: &mut Table) -> Result<u32> {
+    let from = config_version(table)?;
+    if from > CONFIG_VERSION {
+        bail!(
+            "Config file is from a newer version of mahoraga (schema {}, this build supports {}). \
+             Run `mahoraga update` to upgrade",
+            from,
+            CONFIG_VERSION
+        );
+    }
+
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(table);
+    }
+    table.insert("version".to_string(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(from)
+}
+
+/// v0 -> v1: resetting to defaults used to write empty models and API
+/// versions, so drop them and let the defaults apply again
+fn drop_empty_defaults(table: &mut Table) {
+    drop_empty_keys(table);
+    if let Some(Value::Table(profiles)) = table.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            if let Value::Table(profile) = profile {
+                drop_empty_keys(profile);
+            }
+        }
+    }
+}
+
+fn drop_empty_keys(setup: &mut Table) {
+    for (section, key) in DEFAULTED_KEYS {
+        if let Some(Value::Table(section)) = setup.get_mut(*section) {
+            if section.get(*key).and_then(Value::as_str) == Some("") {
+                section.remove(*key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Config;
+
+    #[test]
+    fn test_migrate_unversioned_config() {
+        let mut table: Table = toml::from_str(
+            "[openai]\nmodel = \"\"\n\n[profiles.work.azure]\napi_version = \"\"\ndeployment = \"gpt\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut table).unwrap(), 0);
+        let config: Config = table.try_into().unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.openai.model, Config::default().openai.model);
+        assert_eq!(config.profiles["work"].azure.api_version, Config::default().azure.api_version);
+        assert_eq!(config.profiles["work"].azure.deployment, "gpt");
+
+        let mut newer: Table = toml::from_str("version = 999").unwrap();
+        assert!(migrate(&mut newer).is_err());
+    }
+}
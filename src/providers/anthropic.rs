@@ -4,20 +4,23 @@ use reqwest::Client;
 use serde_json::json;
 
 use crate::types::{AnalysisResult, AnthropicConfig};
-use super::{parse_analysis_response, Provider, SYSTEM_PROMPT};
+use super::{api_error_message, model_ids, parse_analysis_response, Provider, TEST_TIMEOUT};
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
 const ANTHROPIC_API_VERSION: &str = "2023-06-01";
 
 pub struct AnthropicProvider {
     config: AnthropicConfig,
+    system_prompt: String,
     client: Client,
 }
 
 impl AnthropicProvider {
-    pub fn new(config: AnthropicConfig) -> Self {
+    pub fn new(config: AnthropicConfig, system_prompt: String) -> Self {
         Self {
             config,
+            system_prompt,
             client: Client::new(),
         }
     }
@@ -33,7 +36,7 @@ impl Provider for AnthropicProvider {
         let body = json!({
             "model": self.config.model,
             "max_tokens": 1000,
-            "system": SYSTEM_PROMPT,
+            "system": self.system_prompt,
             "messages": [
                 {
                     "role": "user",
@@ -71,4 +74,63 @@ impl Provider for AnthropicProvider {
 
         parse_analysis_response(content)
     }
+
+    async fn test_connection(&self) -> Result<()> {
+        if self.config.api_key.is_empty() {
+            anyhow::bail!("Anthropic API key is not configured");
+        }
+
+        // Looking up the model checks both the key and the model name
+        let response = self
+            .client
+            .get(format!("{}/{}", ANTHROPIC_MODELS_URL, self.config.model))
+            .header("x-api-key", &self.config.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .context("Could not reach Anthropic")?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let message = api_error_message(&response.text().await.unwrap_or_default());
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!("Anthropic rejected the API key: {}", message),
+            404 => anyhow::bail!("Model '{}' not found", self.config.model),
+            _ => anyhow::bail!("Anthropic API error ({}): {}", status, message),
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        if self.config.api_key.is_empty() {
+            anyhow::bail!("Anthropic API key is not configured");
+        }
+
+        let response = self
+            .client
+            .get(ANTHROPIC_MODELS_URL)
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &self.config.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .context("Could not reach Anthropic")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = api_error_message(&response.text().await.unwrap_or_default());
+            anyhow::bail!("Failed to list Anthropic models ({}): {}", status, message);
+        }
+
+        // Listed newest first, which is the order worth keeping
+        let response_json: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse Anthropic models")?;
+        Ok(model_ids(&response_json))
+    }
 }
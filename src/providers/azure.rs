@@ -4,17 +4,25 @@ use reqwest::Client;
 use serde_json::json;
 
 use crate::types::{AnalysisResult, AzureConfig};
-use super::{parse_analysis_response, Provider, SYSTEM_PROMPT};
+use super::{api_error_message, model_ids, parse_analysis_response, Provider, TEST_TIMEOUT};
+
+/// Data-plane API version with a deployments listing. Newer versions only
+/// list deployments through the management plane, which needs an Entra ID
+/// token rather than an API key, so this is tried first and `/openai/models`
+/// is the fallback where Azure no longer serves it.
+const DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
 
 pub struct AzureProvider {
     config: AzureConfig,
+    system_prompt: String,
     client: Client,
 }
 
 impl AzureProvider {
-    pub fn new(config: AzureConfig) -> Self {
+    pub fn new(config: AzureConfig, system_prompt: String) -> Self {
         Self {
             config,
+            system_prompt,
             client: Client::new(),
         }
     }
@@ -26,11 +34,19 @@ impl AzureProvider {
             base_url, self.config.deployment, self.config.api_version
         )
     }
-}
 
-#[async_trait]
-impl Provider for AzureProvider {
-    async fn analyze(&self, prompt: &str) -> Result<AnalysisResult> {
+    /// GET a listing endpoint with the API key
+    async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        self.client
+            .get(url)
+            .header("api-key", &self.config.api_key)
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {}", self.config.url))
+    }
+
+    fn check_config(&self) -> Result<()> {
         if self.config.url.is_empty() {
             anyhow::bail!("Azure URL is not configured");
         }
@@ -40,6 +56,14 @@ impl Provider for AzureProvider {
         if self.config.deployment.is_empty() {
             anyhow::bail!("Azure deployment is not configured");
         }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for AzureProvider {
+    async fn analyze(&self, prompt: &str) -> Result<AnalysisResult> {
+        self.check_config()?;
 
         let url = self.build_url();
 
@@ -47,7 +71,7 @@ impl Provider for AzureProvider {
             "messages": [
                 {
                     "role": "system",
-                    "content": SYSTEM_PROMPT
+                    "content": self.system_prompt
                 },
                 {
                     "role": "user",
@@ -85,4 +109,82 @@ impl Provider for AzureProvider {
 
         parse_analysis_response(content)
     }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.check_config()?;
+
+        let body = json!({
+            "messages": [{ "role": "user", "content": "ping" }],
+            "max_tokens": 1
+        });
+
+        let response = self
+            .client
+            .post(self.build_url())
+            .header("api-key", &self.config.api_key)
+            .json(&body)
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {}", self.config.url))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let error_text = response.text().await.unwrap_or_default();
+        let message = api_error_message(&error_text);
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!("Azure rejected the API key: {}", message),
+            404 if error_text.contains("DeploymentNotFound") => {
+                anyhow::bail!("Deployment '{}' not found", self.config.deployment)
+            }
+            404 => anyhow::bail!("Resource not found. Check the Azure URL: {}", message),
+            400 if message.to_lowercase().contains("api version") => {
+                anyhow::bail!("API version '{}' is not supported", self.config.api_version)
+            }
+            _ => anyhow::bail!("Azure API error ({}): {}", status, message),
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        if self.config.url.is_empty() {
+            anyhow::bail!("Azure URL is not configured");
+        }
+        if self.config.api_key.is_empty() {
+            anyhow::bail!("Azure API key is not configured");
+        }
+
+        let base_url = self.config.url.trim_end_matches('/');
+        let url = format!("{}/openai/deployments?api-version={}", base_url, DEPLOYMENTS_API_VERSION);
+        let response = self.get(&url).await?;
+
+        let status = response.status();
+        let response_json: serde_json::Value = if status.is_success() {
+            response.json().await.context("Failed to parse Azure deployments")?
+        } else if matches!(status.as_u16(), 400 | 404) {
+            // Deployments listing retired: list the chat models the resource
+            // offers instead. Deployments are usually named after them.
+            let url = format!("{}/openai/models?api-version={}", base_url, self.config.api_version);
+            let response = self.get(&url).await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let message = api_error_message(&response.text().await.unwrap_or_default());
+                anyhow::bail!("Failed to list Azure models ({}): {}", status, message);
+            }
+            let mut models: serde_json::Value = response.json().await.context("Failed to parse Azure models")?;
+            if let Some(data) = models["data"].as_array_mut() {
+                data.retain(|model| model["capabilities"]["chat_completion"].as_bool() == Some(true));
+            }
+            models
+        } else {
+            let message = api_error_message(&response.text().await.unwrap_or_default());
+            anyhow::bail!("Failed to list Azure deployments ({}): {}", status, message);
+        };
+        let mut deployments = model_ids(&response_json);
+        deployments.sort();
+        deployments.dedup();
+        Ok(deployments)
+    }
 }
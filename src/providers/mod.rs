@@ -6,10 +6,15 @@ pub use azure::AzureProvider;
 pub use openai::OpenAIProvider;
 pub use anthropic::AnthropicProvider;
 
+use std::time::Duration;
+
 use anyhow::Result;
 use async_trait::async_trait;
 
-use crate::types::{AnalysisResult, Config, ProviderType};
+use crate::secrets::resolve_api_key;
+use crate::types::{
+    AnalysisResult, AnthropicConfig, AzureConfig, Config, OpenAIConfig, ProviderType,
+};
 
 /// System prompt for analyzing prompts
 pub const SYSTEM_PROMPT: &str = r#"You are a strict prompt quality analyzer. Your job is to critically evaluate prompts with high standards. Analyze the given prompt and provide:
@@ -61,25 +66,78 @@ IMPORTANT: Be critical. A simple one-liner like "Write code for X" should score
 
 Always provide at least 2-3 specific improvements, even for good prompts."#;
 
+/// System prompt with a project's extra rubric appended, if it has one
+pub fn system_prompt(rubric: &str) -> String {
+    let rubric = rubric.trim();
+    if rubric.is_empty() {
+        SYSTEM_PROMPT.to_string()
+    } else {
+        format!(
+            "{}\n\nAdditional project rubric (apply on top of the criteria above):\n{}",
+            SYSTEM_PROMPT, rubric
+        )
+    }
+}
+
 /// Provider trait for LLM implementations
 #[async_trait]
 pub trait Provider: Send + Sync {
     /// Analyze a prompt and return the analysis result
     async fn analyze(&self, prompt: &str) -> Result<AnalysisResult>;
 
+    /// Send a minimal request to check that the configuration works
+    async fn test_connection(&self) -> Result<()>;
+
+    /// Models (or Azure deployments) available to the configured account
+    async fn list_models(&self) -> Result<Vec<String>>;
 }
 
-/// Create a provider based on configuration
-pub fn create_provider(config: &Config) -> Result<Box<dyn Provider>> {
-    match config.provider.active {
+/// How long a connection test waits for a response
+pub const TEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The `id` of each entry in a `{"data": [...]}` list response
+pub fn model_ids(response: &serde_json::Value) -> Vec<String> {
+    response["data"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|model| model["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The `error.message` from an API error body, or the body itself
+pub fn api_error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Create the active provider based on configuration, resolving its API key
+pub async fn create_provider(config: &Config) -> Result<Box<dyn Provider>> {
+    create_provider_for(config, config.provider.active).await
+}
+
+/// Create a specific provider from its configuration section
+pub async fn create_provider_for(config: &Config, provider: ProviderType) -> Result<Box<dyn Provider>> {
+    let api_key = resolve_api_key(config, provider).await?;
+    let system_prompt = system_prompt(&config.analysis.rubric);
+
+    match provider {
         ProviderType::Azure => {
-            Ok(Box::new(AzureProvider::new(config.azure.clone())))
+            let azure = AzureConfig { api_key, ..config.azure.clone() };
+            Ok(Box::new(AzureProvider::new(azure, system_prompt)))
         }
         ProviderType::OpenAI => {
-            Ok(Box::new(OpenAIProvider::new(config.openai.clone())))
+            let openai = OpenAIConfig { api_key, ..config.openai.clone() };
+            Ok(Box::new(OpenAIProvider::new(openai, system_prompt)))
         }
         ProviderType::Anthropic => {
-            Ok(Box::new(AnthropicProvider::new(config.anthropic.clone())))
+            let anthropic = AnthropicConfig { api_key, ..config.anthropic.clone() };
+            Ok(Box::new(AnthropicProvider::new(anthropic, system_prompt)))
         }
     }
 }
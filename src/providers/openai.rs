@@ -4,19 +4,25 @@ use reqwest::Client;
 use serde_json::json;
 
 use crate::types::{AnalysisResult, OpenAIConfig};
-use super::{parse_analysis_response, Provider, SYSTEM_PROMPT};
+use super::{api_error_message, model_ids, parse_analysis_response, Provider, TEST_TIMEOUT};
 
 const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+
+/// Model families that can't be used for chat completions
+const NON_CHAT_MODELS: &[&str] = &["embedding", "whisper", "tts", "dall-e", "moderation"];
 
 pub struct OpenAIProvider {
     config: OpenAIConfig,
+    system_prompt: String,
     client: Client,
 }
 
 impl OpenAIProvider {
-    pub fn new(config: OpenAIConfig) -> Self {
+    pub fn new(config: OpenAIConfig, system_prompt: String) -> Self {
         Self {
             config,
+            system_prompt,
             client: Client::new(),
         }
     }
@@ -34,7 +40,7 @@ impl Provider for OpenAIProvider {
             "messages": [
                 {
                     "role": "system",
-                    "content": SYSTEM_PROMPT
+                    "content": self.system_prompt
                 },
                 {
                     "role": "user",
@@ -72,4 +78,64 @@ impl Provider for OpenAIProvider {
 
         parse_analysis_response(content)
     }
+
+    async fn test_connection(&self) -> Result<()> {
+        if self.config.api_key.is_empty() {
+            anyhow::bail!("OpenAI API key is not configured");
+        }
+
+        // Looking up the model checks both the key and the model name
+        let response = self
+            .client
+            .get(format!("{}/{}", OPENAI_MODELS_URL, self.config.model))
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .context("Could not reach OpenAI")?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let message = api_error_message(&response.text().await.unwrap_or_default());
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!("OpenAI rejected the API key: {}", message),
+            404 => anyhow::bail!("Model '{}' not found", self.config.model),
+            _ => anyhow::bail!("OpenAI API error ({}): {}", status, message),
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        if self.config.api_key.is_empty() {
+            anyhow::bail!("OpenAI API key is not configured");
+        }
+
+        let response = self
+            .client
+            .get(OPENAI_MODELS_URL)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .timeout(TEST_TIMEOUT)
+            .send()
+            .await
+            .context("Could not reach OpenAI")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = api_error_message(&response.text().await.unwrap_or_default());
+            anyhow::bail!("Failed to list OpenAI models ({}): {}", status, message);
+        }
+
+        let response_json: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse OpenAI models")?;
+        let mut models: Vec<String> = model_ids(&response_json)
+            .into_iter()
+            .filter(|model| !NON_CHAT_MODELS.iter().any(|family| model.contains(family)))
+            .collect();
+        models.sort();
+        Ok(models)
+    }
 }
@@ -0,0 +1,605 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use tokio::process::Command;
+
+use crate::config::{config_dir, write_private_file};
+use crate::types::{Config, ProviderType, SecretStorage};
+
+/// Service name API keys are stored under in the OS keyring
+pub const KEYRING_SERVICE: &str = "mahoraga";
+
+/// A backend that API keys can be stored in, keyed by account name
+pub trait SecretStore {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, secret: &str) -> Result<()>;
+    fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// Secrets kept in the OS keyring
+pub struct KeyringStore;
+
+impl KeyringStore {
+    fn entry(account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, account).context("Failed to open keyring entry")
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match Self::entry(account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e).context("Failed to read from keyring"),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        Self::entry(account)?
+            .set_password(secret)
+            .context("Failed to write to keyring")
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match Self::entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e).context("Failed to delete from keyring"),
+        }
+    }
+}
+
+/// Secrets kept in a separate TOML file, readable only by the owner
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read secrets file: {}", self.path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse secrets file: {}", self.path.display()))
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let content = toml::to_string_pretty(secrets).context("Failed to serialize secrets")?;
+
+        write_private_file(&self.path, &content)
+            .with_context(|| format!("Failed to write secrets file: {}", self.path.display()))
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        Ok(self.read()?.remove(account))
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        secrets.insert(account.to_string(), secret.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        if secrets.remove(account).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// What this process last read from or wrote to a store, per account;
+/// `None` when the entry is known not to exist
+type StoreCache = Mutex<HashMap<String, Option<String>>>;
+
+/// A store that skips writes which wouldn't change anything, so saving the
+/// config doesn't touch the keyring (and prompt for access) for every key
+struct CachedStore {
+    inner: Box<dyn SecretStore>,
+    cache: &'static StoreCache,
+}
+
+impl CachedStore {
+    fn cached(&self, account: &str) -> Option<Option<String>> {
+        self.cache.lock().ok()?.get(account).cloned()
+    }
+
+    fn remember(&self, account: &str, secret: Option<&str>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(account.to_string(), secret.map(str::to_string));
+        }
+    }
+}
+
+impl SecretStore for CachedStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        if let Some(secret) = self.cached(account) {
+            return Ok(secret);
+        }
+        let secret = self.inner.get(account)?;
+        self.remember(account, secret.as_deref());
+        Ok(secret)
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        if self.cached(account).flatten().as_deref() == Some(secret) {
+            return Ok(());
+        }
+        self.inner.set(account, secret)?;
+        self.remember(account, Some(secret));
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        if self.cached(account) == Some(None) {
+            return Ok(());
+        }
+        self.inner.delete(account)?;
+        self.remember(account, None);
+        Ok(())
+    }
+}
+
+/// Open the secret store backing the given storage kind
+pub fn open_store(storage: SecretStorage) -> Result<Box<dyn SecretStore>> {
+    static KEYRING_CACHE: OnceLock<StoreCache> = OnceLock::new();
+    static FILE_CACHE: OnceLock<StoreCache> = OnceLock::new();
+
+    let (inner, cache): (Box<dyn SecretStore>, _) = match storage {
+        SecretStorage::Keyring => (Box::new(KeyringStore), &KEYRING_CACHE),
+        SecretStorage::File => (Box::new(FileStore::new(config_dir()?.join("secrets.toml"))), &FILE_CACHE),
+        SecretStorage::Plaintext => bail!("Plaintext storage has no secret store"),
+    };
+    Ok(Box::new(CachedStore {
+        inner,
+        cache: cache.get_or_init(Default::default),
+    }))
+}
+
+fn storage_description(storage: SecretStorage) -> &'static str {
+    match storage {
+        SecretStorage::Plaintext => "config file",
+        SecretStorage::Keyring => "OS keyring",
+        SecretStorage::File => "secrets file",
+    }
+}
+
+/// Every API key in the config, including profiles, with its account name
+/// and display name
+fn api_keys_mut(config: &mut Config) -> Vec<(String, String, &mut String)> {
+    let mut keys = vec![
+        ("azure".to_string(), "Azure".to_string(), &mut config.azure.api_key),
+        ("openai".to_string(), "OpenAI".to_string(), &mut config.openai.api_key),
+        ("anthropic".to_string(), "Anthropic".to_string(), &mut config.anthropic.api_key),
+    ];
+    for (profile_name, profile) in config.profiles.iter_mut() {
+        keys.extend([
+            (format!("{}.azure", profile_name), format!("Azure ({})", profile_name), &mut profile.azure.api_key),
+            (format!("{}.openai", profile_name), format!("OpenAI ({})", profile_name), &mut profile.openai.api_key),
+            (
+                format!("{}.anthropic", profile_name),
+                format!("Anthropic ({})", profile_name),
+                &mut profile.anthropic.api_key,
+            ),
+        ]);
+    }
+    keys
+}
+
+/// Parse a stored key reference such as `keyring:openai`
+fn parse_reference(value: &str) -> Option<(SecretStorage, &str)> {
+    let (kind, account) = value.split_once(':')?;
+    let storage = match kind {
+        "keyring" => SecretStorage::Keyring,
+        "file" => SecretStorage::File,
+        _ => return None,
+    };
+    Some((storage, account))
+}
+
+/// Every stored key a raw config file refers to, including its profiles'.
+/// Read from the TOML as-is, so it works for any config version.
+pub fn stored_key_references(config: &toml::Table) -> Vec<(SecretStorage, String)> {
+    fn collect(table: &toml::Table, references: &mut Vec<(SecretStorage, String)>) {
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(table) => collect(table, references),
+                toml::Value::String(value) if key == "api_key" => {
+                    if let Some((storage, account)) = parse_reference(value) {
+                        references.push((storage, account.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut references = Vec::new();
+    collect(config, &mut references);
+    references.sort_by(|a, b| (a.0.as_str(), &a.1).cmp(&(b.0.as_str(), &b.1)));
+    references.dedup();
+    references
+}
+
+/// Accounts of the keyring entries a raw config file refers to
+pub fn keyring_accounts(config: &toml::Table) -> Vec<String> {
+    stored_key_references(config)
+        .into_iter()
+        .filter(|(storage, _)| *storage == SecretStorage::Keyring)
+        .map(|(_, account)| account)
+        .collect()
+}
+
+/// Delete stored keys that the old config file referred to but the new one
+/// doesn't, e.g. after removing a profile or resetting the config
+pub fn delete_orphaned_secrets(old: &toml::Table, new: &toml::Table) -> Result<()> {
+    delete_orphaned_secrets_with(old, new, &open_store)
+}
+
+/// Replace secret references in a loaded config with the stored keys.
+///
+/// Returns `true` if any key lives somewhere other than the configured
+/// storage, e.g. plaintext keys left in the config, so it needs migrating.
+pub fn resolve_secrets(config: &mut Config) -> Result<bool> {
+    resolve_secrets_with(config, &open_store)
+}
+
+/// Copy of `config` to write to disk, with API keys moved into the
+/// configured secret store and replaced by references.
+pub fn externalize_secrets(config: &Config) -> Result<Config> {
+    externalize_secrets_with(config, &open_store)
+}
+
+/// Where a provider's API key comes from, in order of precedence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// Output of the `api_key_cmd` shell command
+    Command(String),
+    /// An environment variable, from `api_key_env` or the provider default
+    Env(String),
+    /// The `api_key` value, from the config file or secret store
+    Stored(SecretStorage),
+    /// No key configured anywhere
+    Missing,
+}
+
+impl KeySource {
+    /// Short description for the settings screen
+    pub fn describe(&self) -> String {
+        match self {
+            KeySource::Command(cmd) => format!("from command: {}", cmd),
+            KeySource::Env(var) => format!("from ${}", var),
+            KeySource::Stored(storage) => format!("in {}", storage_description(*storage)),
+            KeySource::Missing => "not configured".to_string(),
+        }
+    }
+}
+
+/// The `api_key`, `api_key_env` and `api_key_cmd` values for a provider
+pub fn key_fields(config: &Config, provider: ProviderType) -> (&str, &str, &str) {
+    match provider {
+        ProviderType::Azure => (&config.azure.api_key, &config.azure.api_key_env, &config.azure.api_key_cmd),
+        ProviderType::OpenAI => (&config.openai.api_key, &config.openai.api_key_env, &config.openai.api_key_cmd),
+        ProviderType::Anthropic => (
+            &config.anthropic.api_key,
+            &config.anthropic.api_key_env,
+            &config.anthropic.api_key_cmd,
+        ),
+    }
+}
+
+/// Determine where a provider's API key comes from.
+///
+/// An explicit `api_key_cmd` or `api_key_env` wins over `api_key`; the
+/// provider's conventional environment variable is only a fallback.
+pub fn key_source(config: &Config, provider: ProviderType) -> KeySource {
+    let (api_key, api_key_env, api_key_cmd) = key_fields(config, provider);
+
+    if !api_key_cmd.is_empty() {
+        KeySource::Command(api_key_cmd.to_string())
+    } else if !api_key_env.is_empty() {
+        KeySource::Env(api_key_env.to_string())
+    } else if !api_key.is_empty() {
+        KeySource::Stored(config.secrets.storage)
+    } else if env::var(provider.default_key_env()).is_ok_and(|key| !key.is_empty()) {
+        KeySource::Env(provider.default_key_env().to_string())
+    } else {
+        KeySource::Missing
+    }
+}
+
+/// Characters left visible at the end of a masked secret
+const VISIBLE_SECRET_CHARS: usize = 4;
+
+/// Hide a secret for display, keeping its last few characters so keys can
+/// be told apart. The mask has a fixed width so it doesn't leak the length.
+pub fn mask_secret(secret: &str) -> String {
+    let len = secret.chars().count();
+    if len == 0 {
+        return String::new();
+    }
+    // Short values would be mostly revealed, so hide them entirely
+    if len <= VISIBLE_SECRET_CHARS * 2 {
+        return "•".repeat(8);
+    }
+    let tail: String = secret.chars().skip(len - VISIBLE_SECRET_CHARS).collect();
+    format!("{}{}", "•".repeat(8), tail)
+}
+
+/// Hide a secret as it's being typed: one bullet per character, so edits
+/// show up, with the last few characters visible once the key is long enough
+pub fn mask_secret_input(secret: &str) -> String {
+    let len = secret.chars().count();
+    let visible = if len > VISIBLE_SECRET_CHARS * 2 { VISIBLE_SECRET_CHARS } else { 0 };
+    secret
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < len - visible { '•' } else { c })
+        .collect()
+}
+
+/// Longest an `api_key_cmd` may run, e.g. while a password manager waits
+/// for the user to unlock it
+const KEY_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Resolve a provider's API key from its configured source
+pub async fn resolve_api_key(config: &Config, provider: ProviderType) -> Result<String> {
+    match key_source(config, provider) {
+        KeySource::Command(cmd) => run_key_command(&cmd).await,
+        KeySource::Env(var) => env::var(&var)
+            .with_context(|| format!("Environment variable {} is not set", var)),
+        KeySource::Stored(_) => Ok(key_fields(config, provider).0.to_string()),
+        KeySource::Missing => Ok(String::new()),
+    }
+}
+
+/// Run an `api_key_cmd` without blocking the async runtime
+async fn run_key_command(cmd: &str) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(KEY_COMMAND_TIMEOUT, output)
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "api_key_cmd did not finish within {} seconds: {}",
+                KEY_COMMAND_TIMEOUT.as_secs(),
+                cmd
+            )
+        })?
+        .with_context(|| format!("Failed to run api_key_cmd: {}", cmd))?;
+
+    if !output.status.success() {
+        bail!(
+            "api_key_cmd exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let key = String::from_utf8(output.stdout).context("api_key_cmd printed invalid UTF-8")?;
+    Ok(key.trim().to_string())
+}
+
+type StoreOpener<'a> = dyn Fn(SecretStorage) -> Result<Box<dyn SecretStore>> + 'a;
+
+fn resolve_secrets_with(config: &mut Config, open: &StoreOpener<'_>) -> Result<bool> {
+    let storage = config.secrets.storage;
+    let mut needs_migration = false;
+
+    for (_, name, key) in api_keys_mut(config) {
+        if let Some((kind, account)) = parse_reference(key) {
+            let secret = open(kind)?.get(account).with_context(|| {
+                format!("Failed to read {} API key from the {}", name, storage_description(kind))
+            })?;
+            // Loading on with an empty key would erase the reference on the next save
+            *key = secret.with_context(|| {
+                format!(
+                    "{} API key is missing from the {} (no entry '{}'). \
+                     Run `mahoraga config edit` to replace or clear it",
+                    name,
+                    storage_description(kind),
+                    account
+                )
+            })?;
+            needs_migration |= kind != storage;
+        } else if !key.is_empty() && storage != SecretStorage::Plaintext {
+            needs_migration = true;
+        }
+    }
+
+    Ok(needs_migration)
+}
+
+fn externalize_secrets_with(config: &Config, open: &StoreOpener<'_>) -> Result<Config> {
+    let mut stored = config.clone();
+    let storage = config.secrets.storage;
+    if storage == SecretStorage::Plaintext {
+        return Ok(stored);
+    }
+
+    let store = open(storage)?;
+    for (account, name, key) in api_keys_mut(&mut stored) {
+        let context = || format!("Failed to store {} API key in the {}", name, storage_description(storage));
+        // Cleared keys are removed from the store once the saved config no
+        // longer refers to them, see `delete_orphaned_secrets`
+        if !key.is_empty() {
+            store.set(&account, key).with_context(context)?;
+            *key = format!("{}:{}", storage.as_str(), account);
+        }
+    }
+
+    Ok(stored)
+}
+
+fn delete_orphaned_secrets_with(old: &toml::Table, new: &toml::Table, open: &StoreOpener<'_>) -> Result<()> {
+    let kept = stored_key_references(new);
+    for (storage, account) in stored_key_references(old) {
+        if !kept.contains(&(storage, account.clone())) {
+            open(storage)?.delete(&account).with_context(|| {
+                format!("Failed to remove unused key '{}' from the {}", account, storage_description(storage))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_round_trip_through_file_store() {
+        let path = std::env::temp_dir().join(format!("mahoraga-secrets-{}.toml", std::process::id()));
+        let open = |_: SecretStorage| -> Result<Box<dyn SecretStore>> {
+            Ok(Box::new(FileStore::new(path.clone())))
+        };
+
+        let mut config = Config::default();
+        config.secrets.storage = SecretStorage::File;
+        config.openai.api_key = "sk-test".to_string();
+
+        // Plaintext keys are flagged for migration
+        assert!(resolve_secrets_with(&mut config.clone(), &open).unwrap());
+
+        let mut stored = externalize_secrets_with(&config, &open).unwrap();
+        assert_eq!(stored.openai.api_key, "file:openai");
+        assert!(stored.azure.api_key.is_empty());
+
+        assert!(!resolve_secrets_with(&mut stored, &open).unwrap());
+        assert_eq!(stored.openai.api_key, "sk-test");
+
+        // A reference to an entry that no longer exists is an error
+        let mut dangling = Config::default();
+        dangling.anthropic.api_key = "file:anthropic".to_string();
+        let err = resolve_secrets_with(&mut dangling, &open).unwrap_err();
+        assert!(err.to_string().contains("Anthropic API key is missing"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_cached_store_skips_unchanged_writes() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct CountingStore(Rc<Cell<usize>>);
+        impl SecretStore for CountingStore {
+            fn get(&self, _: &str) -> Result<Option<String>> {
+                Ok(None)
+            }
+            fn set(&self, _: &str, _: &str) -> Result<()> {
+                self.0.set(self.0.get() + 1);
+                Ok(())
+            }
+            fn delete(&self, _: &str) -> Result<()> {
+                self.0.set(self.0.get() + 1);
+                Ok(())
+            }
+        }
+
+        let writes = Rc::new(Cell::new(0));
+        let store = CachedStore {
+            inner: Box::new(CountingStore(writes.clone())),
+            cache: Box::leak(Box::default()),
+        };
+        store.set("openai", "sk-a").unwrap();
+        store.set("openai", "sk-a").unwrap();
+        assert_eq!(writes.get(), 1);
+        store.set("openai", "sk-b").unwrap();
+        store.delete("openai").unwrap();
+        store.delete("openai").unwrap();
+        assert_eq!(writes.get(), 3);
+        assert_eq!(store.get("openai").unwrap(), None);
+    }
+
+    #[test]
+    fn test_delete_orphaned_secrets() {
+        let path = std::env::temp_dir().join(format!("mahoraga-orphans-{}.toml", std::process::id()));
+        let open = |_: SecretStorage| -> Result<Box<dyn SecretStore>> {
+            Ok(Box::new(FileStore::new(path.clone())))
+        };
+        let store = FileStore::new(path.clone());
+        store.set("openai", "sk-kept").unwrap();
+        store.set("work.openai", "sk-removed").unwrap();
+
+        let old: toml::Table = toml::from_str(
+            "[openai]\napi_key = \"file:openai\"\n[profiles.work.openai]\napi_key = \"file:work.openai\"\n",
+        )
+        .unwrap();
+        let new: toml::Table = toml::from_str("[openai]\napi_key = \"file:openai\"\n").unwrap();
+        delete_orphaned_secrets_with(&old, &new, &open).unwrap();
+
+        assert_eq!(store.get("openai").unwrap().as_deref(), Some("sk-kept"));
+        assert_eq!(store.get("work.openai").unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_keyring_accounts() {
+        let config: toml::Table = toml::from_str(
+            r#"
+            [openai]
+            api_key = "keyring:openai"
+            [anthropic]
+            api_key = "file:anthropic"
+            [profiles.work.azure]
+            api_key = "keyring:work.azure"
+            api_key_cmd = "keyring:not-a-key"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(keyring_accounts(&config), ["openai", "work.azure"]);
+    }
+
+    #[test]
+    fn test_mask_secret() {
+        assert_eq!(mask_secret(""), "");
+        assert_eq!(mask_secret("sk-short"), "••••••••");
+        assert_eq!(mask_secret("sk-proj-abcdefghijklmnop"), "••••••••mnop");
+
+        assert_eq!(mask_secret_input(""), "");
+        assert_eq!(mask_secret_input("sk-ab"), "•••••");
+        assert_eq!(mask_secret_input("sk-abcdefgh"), "•••••••efgh");
+    }
+
+    #[tokio::test]
+    async fn test_key_source_precedence() {
+        let mut config = Config::default();
+        config.openai.api_key = "sk-stored".to_string();
+        assert_eq!(
+            key_source(&config, ProviderType::OpenAI),
+            KeySource::Stored(SecretStorage::Plaintext)
+        );
+
+        config.openai.api_key_env = "MAHORAGA_TEST_KEY".to_string();
+        assert_eq!(
+            key_source(&config, ProviderType::OpenAI),
+            KeySource::Env("MAHORAGA_TEST_KEY".to_string())
+        );
+
+        config.openai.api_key_cmd = "echo sk-from-cmd".to_string();
+        assert_eq!(resolve_api_key(&config, ProviderType::OpenAI).await.unwrap(), "sk-from-cmd");
+    }
+}
@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::migrations::CONFIG_VERSION;
+
 /// The active LLM provider
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderType {
     #[default]
@@ -11,6 +15,19 @@ pub enum ProviderType {
 }
 
 impl ProviderType {
+    pub fn all() -> &'static [ProviderType] {
+        &[ProviderType::Azure, ProviderType::OpenAI, ProviderType::Anthropic]
+    }
+
+    /// Identifier used in the config file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderType::Azure => "azure",
+            ProviderType::OpenAI => "openai",
+            ProviderType::Anthropic => "anthropic",
+        }
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             ProviderType::Azure => "Azure OpenAI",
@@ -19,6 +36,15 @@ impl ProviderType {
         }
     }
 
+    /// Environment variable checked for an API key when none is configured
+    pub fn default_key_env(&self) -> &'static str {
+        match self {
+            ProviderType::Azure => "AZURE_OPENAI_API_KEY",
+            ProviderType::OpenAI => "OPENAI_API_KEY",
+            ProviderType::Anthropic => "ANTHROPIC_API_KEY",
+        }
+    }
+
     pub fn next(&self) -> ProviderType {
         match self {
             ProviderType::Azure => ProviderType::OpenAI,
@@ -26,6 +52,14 @@ impl ProviderType {
             ProviderType::Anthropic => ProviderType::Azure,
         }
     }
+
+    pub fn prev(&self) -> ProviderType {
+        match self {
+            ProviderType::Azure => ProviderType::Anthropic,
+            ProviderType::OpenAI => ProviderType::Azure,
+            ProviderType::Anthropic => ProviderType::OpenAI,
+        }
+    }
 }
 
 impl std::fmt::Display for ProviderType {
@@ -35,12 +69,18 @@ impl std::fmt::Display for ProviderType {
 }
 
 /// Azure OpenAI configuration
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AzureConfig {
     #[serde(default)]
     pub url: String,
     #[serde(default)]
     pub api_key: String,
+    /// Environment variable to read the API key from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_env: String,
+    /// Shell command whose output is the API key
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_cmd: String,
     #[serde(default)]
     pub deployment: String,
     #[serde(default = "default_azure_api_version")]
@@ -51,11 +91,30 @@ fn default_azure_api_version() -> String {
     "2024-02-15-preview".to_string()
 }
 
+impl Default for AzureConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            api_key: String::new(),
+            api_key_env: String::new(),
+            api_key_cmd: String::new(),
+            deployment: String::new(),
+            api_version: default_azure_api_version(),
+        }
+    }
+}
+
 /// OpenAI configuration
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIConfig {
     #[serde(default)]
     pub api_key: String,
+    /// Environment variable to read the API key from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_env: String,
+    /// Shell command whose output is the API key
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_cmd: String,
     #[serde(default = "default_openai_model")]
     pub model: String,
 }
@@ -64,11 +123,28 @@ fn default_openai_model() -> String {
     "gpt-4".to_string()
 }
 
+impl Default for OpenAIConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            api_key_env: String::new(),
+            api_key_cmd: String::new(),
+            model: default_openai_model(),
+        }
+    }
+}
+
 /// Anthropic configuration
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnthropicConfig {
     #[serde(default)]
     pub api_key: String,
+    /// Environment variable to read the API key from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_env: String,
+    /// Shell command whose output is the API key
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key_cmd: String,
     #[serde(default = "default_anthropic_model")]
     pub model: String,
 }
@@ -77,6 +153,48 @@ fn default_anthropic_model() -> String {
     "claude-sonnet-4-20250514".to_string()
 }
 
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            api_key_env: String::new(),
+            api_key_cmd: String::new(),
+            model: default_anthropic_model(),
+        }
+    }
+}
+
+/// Where API keys are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretStorage {
+    /// In `config.toml` itself
+    #[default]
+    Plaintext,
+    /// In the OS keyring (Secret Service on Linux, Keychain on macOS)
+    Keyring,
+    /// In a separate `secrets.toml` next to the config
+    File,
+}
+
+impl SecretStorage {
+    /// Identifier used in the config file and in secret references
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretStorage::Plaintext => "plaintext",
+            SecretStorage::Keyring => "keyring",
+            SecretStorage::File => "file",
+        }
+    }
+}
+
+/// Secret storage configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    #[serde(default)]
+    pub storage: SecretStorage,
+}
+
 /// Provider selection configuration
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProviderSelection {
@@ -84,9 +202,50 @@ pub struct ProviderSelection {
     pub active: ProviderType,
 }
 
-/// Main configuration structure
+/// How prompts are analyzed, usually set per project in `.mahoraga.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisConfig {
+    /// Extra scoring rubric appended to the system prompt
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub rubric: String,
+    /// Score below which a prompt is flagged as not good enough
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<u8>,
+}
+
+/// Self-update configuration
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// Releases API to update from instead of GitHub, e.g. an internal mirror
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub release_url: String,
+}
+
+/// A named provider setup, switched to with `/profile` or `--profile`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub provider: ProviderSelection,
+    #[serde(default)]
+    pub azure: AzureConfig,
+    #[serde(default)]
+    pub openai: OpenAIConfig,
+    #[serde(default)]
+    pub anthropic: AnthropicConfig,
+}
+
+/// Main configuration structure
+///
+/// The top-level provider sections are the setup in use. When a profile is
+/// active they mirror that profile, and are written back to it on save.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version, used to migrate files written by older releases
+    #[serde(default)]
+    pub version: u32,
+    /// Name of the active profile, empty if none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub active_profile: String,
     #[serde(default)]
     pub provider: ProviderSelection,
     #[serde(default)]
@@ -95,10 +254,40 @@ pub struct Config {
     pub openai: OpenAIConfig,
     #[serde(default)]
     pub anthropic: AnthropicConfig,
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Active profile name and setup from the file, kept while `--profile`
+    /// picks another one for this run so saving doesn't persist the switch
+    #[serde(skip)]
+    pub file_profile: Option<(String, Profile)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            active_profile: String::new(),
+            provider: ProviderSelection::default(),
+            azure: AzureConfig::default(),
+            openai: OpenAIConfig::default(),
+            anthropic: AnthropicConfig::default(),
+            analysis: AnalysisConfig::default(),
+            secrets: SecretsConfig::default(),
+            update: UpdateConfig::default(),
+            profiles: BTreeMap::new(),
+            file_profile: None,
+        }
+    }
 }
 
 /// Result of prompt analysis from LLM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AnalysisResult {
     /// Quality score from 0 to 100
     pub score: u8,
@@ -108,16 +297,6 @@ pub struct AnalysisResult {
     pub unclear_parts: Vec<String>,
 }
 
-impl Default for AnalysisResult {
-    fn default() -> Self {
-        Self {
-            score: 0,
-            improvements: Vec::new(),
-            unclear_parts: Vec::new(),
-        }
-    }
-}
-
 /// Application screen states
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Screen {
@@ -143,7 +322,11 @@ pub enum Command {
     Clear,
     Exit,
     Provider,
+    Profile,
     Default,
+    Copy,
+    CopyPrompt,
+    CopyImprovements,
 }
 
 impl Command {
@@ -151,6 +334,10 @@ impl Command {
         &[
             Command::Settings,
             Command::Provider,
+            Command::Profile,
+            Command::Copy,
+            Command::CopyPrompt,
+            Command::CopyImprovements,
             Command::Clear,
             Command::Default,
             Command::Exit,
@@ -163,7 +350,11 @@ impl Command {
             Command::Clear => "/clear",
             Command::Exit => "/exit",
             Command::Provider => "/provider",
+            Command::Profile => "/profile",
             Command::Default => "/default",
+            Command::Copy => "/copy",
+            Command::CopyPrompt => "/copy-prompt",
+            Command::CopyImprovements => "/copy-improvements",
         }
     }
 
@@ -173,7 +364,11 @@ impl Command {
             Command::Clear => "Clear current prompt",
             Command::Exit => "Exit the application",
             Command::Provider => "Switch active provider",
+            Command::Profile => "Switch profile (/profile <name>, or /profile new <name> to create)",
             Command::Default => "Reset to default settings",
+            Command::Copy => "Copy analysis as Markdown",
+            Command::CopyPrompt => "Copy analyzed prompt",
+            Command::CopyImprovements => "Copy list of improvements",
         }
     }
 }
@@ -194,12 +389,13 @@ pub enum SettingsField {
     AnthropicApiKey,
     AnthropicModel,
     // Action buttons
+    Test,
     Save,
     Cancel,
 }
 
 impl SettingsField {
-    /// Get fields for a specific provider
+    /// Fields on a provider's settings tab, after the active-provider choice
     pub fn fields_for_provider(provider: ProviderType) -> Vec<SettingsField> {
         let mut fields = vec![SettingsField::Provider];
 
@@ -226,13 +422,13 @@ impl SettingsField {
             }
         }
 
-        fields.extend([SettingsField::Save, SettingsField::Cancel]);
+        fields.extend([SettingsField::Test, SettingsField::Save, SettingsField::Cancel]);
         fields
     }
 
     pub fn label(&self) -> &'static str {
         match self {
-            SettingsField::Provider => "Provider",
+            SettingsField::Provider => "Active Provider",
             SettingsField::AzureUrl => "Azure URL",
             SettingsField::AzureApiKey => "API Key",
             SettingsField::AzureDeployment => "Deployment",
@@ -241,19 +437,56 @@ impl SettingsField {
             SettingsField::OpenAIModel => "Model",
             SettingsField::AnthropicApiKey => "API Key",
             SettingsField::AnthropicModel => "Model",
+            SettingsField::Test => "Test",
             SettingsField::Save => "Save",
             SettingsField::Cancel => "Cancel",
         }
     }
 
     pub fn is_button(&self) -> bool {
-        matches!(self, SettingsField::Save | SettingsField::Cancel)
+        matches!(self, SettingsField::Test | SettingsField::Save | SettingsField::Cancel)
     }
 
     pub fn is_provider_selector(&self) -> bool {
         matches!(self, SettingsField::Provider)
     }
 
+    /// Key this field edits, as used by `mahoraga config`
+    pub fn config_key(&self) -> Option<&'static str> {
+        match self {
+            SettingsField::AzureUrl => Some("azure.url"),
+            SettingsField::AzureApiKey => Some("azure.api_key"),
+            SettingsField::AzureDeployment => Some("azure.deployment"),
+            SettingsField::AzureApiVersion => Some("azure.api_version"),
+            SettingsField::OpenAIApiKey => Some("openai.api_key"),
+            SettingsField::OpenAIModel => Some("openai.model"),
+            SettingsField::AnthropicApiKey => Some("anthropic.api_key"),
+            SettingsField::AnthropicModel => Some("anthropic.model"),
+            SettingsField::Provider => Some("provider.active"),
+            SettingsField::Test | SettingsField::Save | SettingsField::Cancel => None,
+        }
+    }
+
+    /// Provider whose model (or Azure deployment) this field picks
+    pub fn model_provider(&self) -> Option<ProviderType> {
+        match self {
+            SettingsField::AzureDeployment => Some(ProviderType::Azure),
+            SettingsField::OpenAIModel => Some(ProviderType::OpenAI),
+            SettingsField::AnthropicModel => Some(ProviderType::Anthropic),
+            _ => None,
+        }
+    }
+
+    /// Provider whose API key this field edits
+    pub fn api_key_provider(&self) -> Option<ProviderType> {
+        match self {
+            SettingsField::AzureApiKey => Some(ProviderType::Azure),
+            SettingsField::OpenAIApiKey => Some(ProviderType::OpenAI),
+            SettingsField::AnthropicApiKey => Some(ProviderType::Anthropic),
+            _ => None,
+        }
+    }
+
     pub fn is_password(&self) -> bool {
         matches!(
             self,
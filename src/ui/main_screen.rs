@@ -1,24 +1,67 @@
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Paragraph, Widget},
+    widgets::{
+        Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget,
+    },
 };
 
 use crate::types::{AnalysisResult, AppState, ProviderType};
 use crate::ui::theme;
 use crate::ui::widgets::{CommandMenu, Feedback, Header, PromptInput, ScoreDisplay};
 
+/// Width of the results column
+const RESULTS_WIDTH: u16 = 60;
+
+/// Height of the score display above the feedback
+const SCORE_HEIGHT: u16 = 4;
+
+/// Scroll state for the main screen, updated on each render
+#[derive(Debug, Clone, Default)]
+pub struct MainScreenState {
+    /// First visible line of the results area
+    pub results_scroll: u16,
+    /// Largest valid `results_scroll` for the last rendered layout
+    pub results_max_scroll: u16,
+    /// Results viewport from the last render
+    pub results_area: Rect,
+    /// Prompt text area from the last render
+    pub prompt_area: Rect,
+    /// First visible prompt line from the last render
+    pub prompt_scroll: u16,
+    /// Command menu entries area from the last render, if the menu was shown
+    pub command_menu_area: Option<Rect>,
+}
+
+impl MainScreenState {
+    /// Scroll the results by `delta` lines, clamped to the content
+    pub fn scroll_results(&mut self, delta: i32) {
+        let offset = (self.results_scroll as i32 + delta).clamp(0, self.results_max_scroll as i32);
+        self.results_scroll = offset as u16;
+    }
+
+    /// Number of lines to move for PageUp/PageDown
+    pub fn results_page(&self) -> i32 {
+        self.results_area.height.saturating_sub(1).max(1) as i32
+    }
+}
+
 pub struct MainScreen<'a> {
     prompt: &'a str,
     cursor_position: usize,
     result: Option<&'a AnalysisResult>,
     state: AppState,
     error: Option<&'a str>,
+    notice: Option<&'a str>,
+    notice_is_warning: bool,
     command_selected: usize,
     command_filter: String,
     active_provider: ProviderType,
+    active_profile: &'a str,
+    min_score: Option<u8>,
+    update_available: Option<&'a str>,
     analyzing_word: &'a str,
     analyzing_frame: u8,
 }
@@ -31,9 +74,14 @@ impl<'a> MainScreen<'a> {
             result: None,
             state: AppState::Idle,
             error: None,
+            notice: None,
+            notice_is_warning: false,
             command_selected: 0,
             command_filter: String::new(),
             active_provider: ProviderType::Azure,
+            active_profile: "",
+            min_score: None,
+            update_available: None,
             analyzing_word: "",
             analyzing_frame: 0,
         }
@@ -54,6 +102,12 @@ impl<'a> MainScreen<'a> {
         self
     }
 
+    pub fn notice(mut self, notice: Option<&'a str>, is_warning: bool) -> Self {
+        self.notice = notice;
+        self.notice_is_warning = is_warning;
+        self
+    }
+
     pub fn command_menu(mut self, selected: usize, filter: String) -> Self {
         self.command_selected = selected;
         self.command_filter = filter;
@@ -65,6 +119,21 @@ impl<'a> MainScreen<'a> {
         self
     }
 
+    pub fn profile(mut self, profile: &'a str) -> Self {
+        self.active_profile = profile;
+        self
+    }
+
+    pub fn min_score(mut self, min_score: Option<u8>) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    pub fn update_available(mut self, version: Option<&'a str>) -> Self {
+        self.update_available = version;
+        self
+    }
+
     pub fn analyzing_animation(mut self, word: &'a str, frame: u8) -> Self {
         self.analyzing_word = word;
         self.analyzing_frame = frame;
@@ -72,8 +141,23 @@ impl<'a> MainScreen<'a> {
     }
 }
 
-impl Widget for MainScreen<'_> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
+/// Render the score and feedback into an off-screen buffer of their full height
+fn render_results(result: &AnalysisResult, min_score: Option<u8>, width: u16) -> Buffer {
+    let feedback = Feedback::new(&result.improvements, &result.unclear_parts);
+    let feedback_height = feedback.calculate_height(width);
+    let mut content = Buffer::empty(Rect::new(0, 0, width, SCORE_HEIGHT + feedback_height));
+
+    ScoreDisplay::new(result.score)
+        .min_score(min_score)
+        .render(Rect::new(0, 0, width, SCORE_HEIGHT), &mut content);
+    feedback.render(Rect::new(0, SCORE_HEIGHT, width, feedback_height), &mut content);
+    content
+}
+
+impl StatefulWidget for MainScreen<'_> {
+    type State = MainScreenState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut MainScreenState) {
         // Determine if we have enough space for the full logo
         let show_logo = area.height >= 22;
         let header_height = Header::height(show_logo);
@@ -81,10 +165,31 @@ impl Widget for MainScreen<'_> {
         // Layout calculations
         let show_command_menu = self.state == AppState::CommandMenu;
         let is_analyzing = self.state == AppState::Analyzing;
+        let show_status = self.error.is_some() || self.notice.is_some();
+        let error_height = if show_status { 1 } else { 0 };
+        let analyzing_height = if is_analyzing { 1 } else { 0 };
 
-        // Calculate dynamic prompt height based on newline count
+        // Space left inside the margin once the fixed rows are placed
+        let available = area
+            .height
+            .saturating_sub(2) // margin
+            .saturating_sub(header_height + 2 + analyzing_height + error_height);
+
+        // Calculate dynamic prompt height based on newline count, but never
+        // let it grow past the screen
         let line_count = self.prompt.matches('\n').count() + 1;
-        let prompt_height = (line_count as u16) + 2; // +2 for borders
+        let prompt_height = ((line_count as u16) + 2) // +2 for borders
+            .max(3)
+            .min(available.max(3));
+
+        // Results above input take whatever space the prompt leaves, and scroll
+        // when they don't fit
+        let results_width = RESULTS_WIDTH.min(area.width.saturating_sub(3)); // margin + scrollbar
+        let results = self.result.map(|result| render_results(result, self.min_score, results_width));
+        let results_height = results
+            .as_ref()
+            .map(|content| content.area.height.min(available.saturating_sub(prompt_height)))
+            .unwrap_or(0);
 
         let mut constraints = vec![
             Constraint::Length(header_height),
@@ -96,21 +201,15 @@ impl Widget for MainScreen<'_> {
             constraints.push(Constraint::Length(1));
         }
 
-        // Results above input (if available)
-        if let Some(result) = &self.result {
-            constraints.push(Constraint::Length(4)); // Score display (fixed height)
-            // Calculate feedback height based on content
-            let feedback_width = 60.min(area.width.saturating_sub(2)); // Match fixed width
-            let feedback = Feedback::new(&result.improvements, &result.unclear_parts);
-            let feedback_height = feedback.calculate_height(feedback_width);
-            constraints.push(Constraint::Length(feedback_height));
+        if results.is_some() {
+            constraints.push(Constraint::Length(results_height));
         }
 
         // Prompt input always at the end
-        constraints.push(Constraint::Length(prompt_height.max(3)));
+        constraints.push(Constraint::Length(prompt_height));
 
-        // Error bar (only shown when there's an error)
-        if self.error.is_some() {
+        // Status bar (only shown when there's an error or notice)
+        if show_status {
             constraints.push(Constraint::Length(1));
         }
 
@@ -123,17 +222,26 @@ impl Widget for MainScreen<'_> {
         let mut chunk_idx = 0;
 
         // Header
-        Header::new(show_logo).render(chunks[chunk_idx], buf);
+        Header::new(show_logo)
+            .update_available(self.update_available)
+            .render(chunks[chunk_idx], buf);
         chunk_idx += 1;
 
         // Provider indicator
-        let provider_line = Line::from(vec![
+        let mut provider_spans = vec![
             Span::styled("Provider: ", Style::default().fg(theme::MUTED)),
             Span::styled(
                 self.active_provider.display_name(),
                 Style::default().fg(theme::PRIMARY),
             ),
-        ]);
+        ];
+        if !self.active_profile.is_empty() {
+            provider_spans.extend([
+                Span::styled("  Profile: ", Style::default().fg(theme::MUTED)),
+                Span::styled(self.active_profile, Style::default().fg(theme::PRIMARY)),
+            ]);
+        }
+        let provider_line = Line::from(provider_spans);
         Paragraph::new(provider_line).render(chunks[chunk_idx], buf);
         chunk_idx += 1;
 
@@ -151,46 +259,72 @@ impl Widget for MainScreen<'_> {
             chunk_idx += 1;
         }
 
-        // Results above input (if available)
-        if let Some(result) = self.result {
-            // Score display with fixed width
-            let score_area = chunks[chunk_idx];
-            let fixed_score_area = Rect {
-                x: score_area.x,
-                y: score_area.y,
-                width: 60.min(score_area.width), // Fixed width of 60
-                height: score_area.height,
+        // Results above input (if available), as a scrollable viewport
+        if let Some(content) = results {
+            let results_chunk = chunks[chunk_idx];
+            let viewport = Rect {
+                width: content.area.width.min(results_chunk.width),
+                ..results_chunk
             };
-            ScoreDisplay::new(result.score).render(fixed_score_area, buf);
-            chunk_idx += 1;
 
-            // Feedback with fixed width
-            let feedback_area = chunks[chunk_idx];
-            let fixed_feedback_area = Rect {
-                x: feedback_area.x,
-                y: feedback_area.y,
-                width: 60.min(feedback_area.width), // Fixed width of 60
-                height: feedback_area.height,
-            };
-            Feedback::new(&result.improvements, &result.unclear_parts)
-                .render(fixed_feedback_area, buf);
+            state.results_area = viewport;
+            state.results_max_scroll = content.area.height.saturating_sub(viewport.height);
+            state.results_scroll = state.results_scroll.min(state.results_max_scroll);
+
+            for y in 0..viewport.height {
+                for x in 0..viewport.width {
+                    if let (Some(src), Some(dst)) = (
+                        content.cell((x, y + state.results_scroll)),
+                        buf.cell_mut((viewport.x + x, viewport.y + y)),
+                    ) {
+                        *dst = src.clone();
+                    }
+                }
+            }
+
+            if state.results_max_scroll > 0 {
+                let scrollbar_area = Rect {
+                    width: (viewport.width + 1).min(results_chunk.width),
+                    ..viewport
+                };
+                let mut scrollbar_state =
+                    ScrollbarState::new(state.results_max_scroll as usize + 1)
+                        .position(state.results_scroll as usize)
+                        .viewport_content_length(viewport.height as usize);
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None)
+                    .thumb_style(Style::default().fg(theme::PRIMARY))
+                    .track_style(Style::default().fg(theme::BORDER))
+                    .render(scrollbar_area, buf, &mut scrollbar_state);
+            }
             chunk_idx += 1;
+        } else {
+            state.results_area = Rect::default();
+            state.results_max_scroll = 0;
+            state.results_scroll = 0;
         }
 
         // Prompt input
-        PromptInput::new(self.prompt, self.cursor_position)
-            .focused(self.state != AppState::CommandMenu)
-            .render(chunks[chunk_idx], buf);
         let prompt_chunk = chunks[chunk_idx];
+        let prompt_input = PromptInput::new(self.prompt, self.cursor_position)
+            .focused(self.state != AppState::CommandMenu);
+        state.prompt_area = PromptInput::text_area(prompt_chunk);
+        state.prompt_scroll = prompt_input.scroll_offset(state.prompt_area.height);
+        prompt_input.render(prompt_chunk, buf);
         chunk_idx += 1;
 
-        // Error bar (only shown when there's an error)
+        // Status bar: errors take precedence over notices
         if let Some(err) = self.error {
             let error_line = Line::from(vec![
                 Span::styled("Error: ", Style::default().fg(theme::ERROR)),
                 Span::styled(err, Style::default().fg(theme::ERROR)),
             ]);
             Paragraph::new(error_line).render(chunks[chunk_idx], buf);
+        } else if let Some(notice) = self.notice {
+            let color = if self.notice_is_warning { theme::WARNING } else { theme::SUCCESS };
+            let notice_line = Line::styled(notice, Style::default().fg(color));
+            Paragraph::new(notice_line).render(chunks[chunk_idx], buf);
         }
 
         // Command menu overlay
@@ -204,7 +338,10 @@ impl Widget for MainScreen<'_> {
             let menu_x = prompt_chunk.x + 1;
 
             let menu_area = Rect::new(menu_x, menu_y, menu_width, menu_height);
+            state.command_menu_area = Some(menu_area.inner(Margin::new(1, 1)));
             menu.render(menu_area, buf);
+        } else {
+            state.command_menu_area = None;
         }
     }
 }
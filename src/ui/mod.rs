@@ -3,5 +3,5 @@ pub mod widgets;
 pub mod main_screen;
 pub mod settings_screen;
 
-pub use main_screen::MainScreen;
-pub use settings_screen::SettingsScreen;
+pub use main_screen::{MainScreen, MainScreenState};
+pub use settings_screen::{ModelPicker, SettingsScreen, SettingsScreenState};
@@ -1,12 +1,14 @@
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Position, Rect},
     style::{Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, Paragraph, Widget},
+    widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget},
 };
 
-use crate::types::{Config, SettingsField};
+use crate::config::{get_config_value, provider_configured, validate_config_value, ProjectConfig, PROJECT_CONFIG_FILE};
+use crate::secrets::{key_source, mask_secret, mask_secret_input, KeySource};
+use crate::types::{Config, ProviderType, SecretStorage, SettingsField};
 use crate::ui::theme;
 
 /// Returns a centered `Rect` of `width` x `height` within `outer`.
@@ -16,29 +18,97 @@ fn centered_rect(width: u16, height: u16, outer: Rect) -> Rect {
     Rect::new(x, y, width.min(outer.width), height.min(outer.height))
 }
 
+/// Most models shown in the dropdown at once
+const PICKER_ROWS: u16 = 8;
+
+/// Dropdown for choosing a model on the settings screen
+#[derive(Debug, Clone, Default)]
+pub struct ModelPicker {
+    /// Text typed to narrow the list, used as the value if nothing matches
+    pub filter: String,
+    /// Index into the matching models
+    pub selected: usize,
+}
+
+impl ModelPicker {
+    /// Models containing the filter text, ignoring case
+    pub fn matches<'m>(&self, models: &'m [String]) -> Vec<&'m str> {
+        let filter = self.filter.to_lowercase();
+        models
+            .iter()
+            .filter(|model| model.to_lowercase().contains(&filter))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Layout of the settings popup, updated on each render
+#[derive(Debug, Clone, Default)]
+pub struct SettingsScreenState {
+    /// Popup area from the last render
+    pub popup_area: Rect,
+    /// Area of each field, in the same order as the settings fields
+    pub field_areas: Vec<Rect>,
+    /// Area of each provider tab title
+    pub tab_areas: Vec<(ProviderType, Rect)>,
+}
+
+impl SettingsScreenState {
+    /// Index of the field at the given screen position
+    pub fn field_at(&self, column: u16, row: u16) -> Option<usize> {
+        let position = Position::new(column, row);
+        self.field_areas.iter().position(|area| area.contains(position))
+    }
+
+    /// Provider tab whose title is at the given screen position
+    pub fn tab_at(&self, column: u16, row: u16) -> Option<ProviderType> {
+        let position = Position::new(column, row);
+        self.tab_areas
+            .iter()
+            .find(|(_, area)| area.contains(position))
+            .map(|(provider, _)| *provider)
+    }
+}
+
 pub struct SettingsScreen<'a> {
     config: &'a Config,
+    tab: ProviderType,
     selected_field: usize,
     editing: bool,
     edit_value: &'a str,
     cursor_position: usize,
+    reveal: bool,
     message: Option<&'a str>,
     is_error: bool,
+    picker: Option<&'a ModelPicker>,
+    picker_models: Option<&'a [String]>,
+    project: Option<&'a ProjectConfig>,
 }
 
 impl<'a> SettingsScreen<'a> {
     pub fn new(config: &'a Config) -> Self {
         Self {
             config,
+            tab: config.provider.active,
             selected_field: 0,
             editing: false,
             edit_value: "",
             cursor_position: 0,
+            reveal: false,
             message: None,
             is_error: false,
+            picker: None,
+            picker_models: None,
+            project: None,
         }
     }
 
+    /// Provider whose settings are being edited
+    pub fn tab(mut self, tab: ProviderType) -> Self {
+        self.tab = tab;
+        self
+    }
+
     pub fn selected(mut self, field: usize) -> Self {
         self.selected_field = field;
         self
@@ -51,58 +121,183 @@ impl<'a> SettingsScreen<'a> {
         self
     }
 
+    /// Show the API key being edited in full
+    pub fn reveal(mut self, reveal: bool) -> Self {
+        self.reveal = reveal;
+        self
+    }
+
     pub fn message(mut self, msg: Option<&'a str>, is_error: bool) -> Self {
         self.message = msg;
         self.is_error = is_error;
         self
     }
 
+    /// Show the model dropdown under the selected field; `models` is `None`
+    /// while they are still loading
+    pub fn model_picker(mut self, picker: Option<&'a ModelPicker>, models: Option<&'a [String]>) -> Self {
+        self.picker = picker;
+        self.picker_models = models;
+        self
+    }
+
+    /// Project config whose overrides take precedence over these settings
+    pub fn project(mut self, project: Option<&'a ProjectConfig>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Note for fields the project config overrides, since edits to them
+    /// won't take effect in this project
+    fn override_hint(&self, field: &SettingsField) -> String {
+        let overridden = field
+            .config_key()
+            .zip(self.project)
+            .is_some_and(|(key, project)| project.overrides_key(key));
+        if overridden {
+            format!(" (overridden by {})", PROJECT_CONFIG_FILE)
+        } else {
+            String::new()
+        }
+    }
+
+    fn render_picker(&self, picker: &ModelPicker, field_area: Rect, bounds: Rect, buf: &mut Buffer) {
+        let matches = self.picker_models.map(|models| picker.matches(models));
+        let list_height = matches
+            .as_ref()
+            .map_or(1, |matches| (matches.len() as u16).clamp(1, PICKER_ROWS));
+        // Filter line + list + borders
+        let height = list_height + 3;
+
+        // Drop down below the field, or up if there's no room
+        let below = field_area.y + field_area.height;
+        let y = if below + height <= bounds.bottom() {
+            below
+        } else {
+            field_area.y.saturating_sub(height).max(bounds.y)
+        };
+        let area = Rect::new(
+            field_area.x + 2,
+            y,
+            field_area.width.saturating_sub(2),
+            height.min(bounds.height),
+        )
+        .intersection(bounds);
+
+        Clear.render(area, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme::PRIMARY));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled("> ", Style::default().fg(theme::MUTED)),
+            Span::styled(picker.filter.as_str(), Style::default().fg(theme::PRIMARY)),
+            Span::styled(" ", Style::default().bg(theme::PRIMARY)),
+        ])];
+
+        match matches {
+            None => lines.push(Line::styled("Loading models...", Style::default().fg(theme::MUTED))),
+            Some(matches) if matches.is_empty() => lines.push(Line::styled(
+                "No matching models (Enter to use as typed)",
+                Style::default().fg(theme::MUTED),
+            )),
+            Some(matches) => {
+                // Scroll so the selection stays visible
+                let offset = picker.selected.saturating_sub(list_height as usize - 1);
+                for (idx, model) in matches.iter().enumerate().skip(offset).take(list_height as usize) {
+                    let style = if idx == picker.selected {
+                        Style::default().fg(theme::BACKGROUND).bg(theme::PRIMARY)
+                    } else {
+                        Style::default().fg(theme::SECONDARY)
+                    };
+                    lines.push(Line::styled(*model, style));
+                }
+            }
+        }
+
+        Paragraph::new(Text::from(lines)).render(inner, buf);
+    }
+
+    /// Provider titles with a configured marker, recording where each is drawn
+    fn render_tabs(&self, area: Rect, buf: &mut Buffer, state: &mut SettingsScreenState) {
+        let mut spans = Vec::new();
+        let mut x = area.x;
+        state.tab_areas.clear();
+
+        for (idx, provider) in ProviderType::all().iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::styled(" │ ", Style::default().fg(theme::BORDER)));
+                x += 3;
+            }
+
+            let name_style = if *provider == self.tab {
+                Style::default()
+                    .fg(theme::PRIMARY)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(theme::SECONDARY)
+            };
+            let (marker, marker_color) = if provider_configured(self.config, *provider) {
+                ("●", theme::SUCCESS)
+            } else {
+                ("○", theme::MUTED)
+            };
+
+            let name = provider.display_name();
+            let width = name.chars().count() as u16 + 2;
+            spans.push(Span::styled(name, name_style));
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(marker, Style::default().fg(marker_color)));
+            state.tab_areas.push((*provider, Rect::new(x, area.y, width, 1).intersection(area)));
+            x += width;
+        }
+
+        let rule = "─".repeat(area.width as usize);
+        let lines = vec![
+            Line::from(spans),
+            Line::styled(rule, Style::default().fg(theme::BORDER)),
+        ];
+        Paragraph::new(Text::from(lines)).render(area, buf);
+    }
+
     fn get_field_value(&self, field: &SettingsField) -> String {
         match field {
             SettingsField::Provider => self.config.provider.active.display_name().to_string(),
             SettingsField::AzureUrl => self.config.azure.url.clone(),
-            SettingsField::AzureApiKey => {
-                if self.config.azure.api_key.is_empty() {
-                    String::new()
-                } else {
-                    "•".repeat(self.config.azure.api_key.len().min(20))
-                }
-            }
+            SettingsField::AzureApiKey => mask_secret(&self.config.azure.api_key),
             SettingsField::AzureDeployment => self.config.azure.deployment.clone(),
             SettingsField::AzureApiVersion => self.config.azure.api_version.clone(),
-            SettingsField::OpenAIApiKey => {
-                if self.config.openai.api_key.is_empty() {
-                    String::new()
-                } else {
-                    "•".repeat(self.config.openai.api_key.len().min(20))
-                }
-            }
+            SettingsField::OpenAIApiKey => mask_secret(&self.config.openai.api_key),
             SettingsField::OpenAIModel => self.config.openai.model.clone(),
-            SettingsField::AnthropicApiKey => {
-                if self.config.anthropic.api_key.is_empty() {
-                    String::new()
-                } else {
-                    "•".repeat(self.config.anthropic.api_key.len().min(20))
-                }
-            }
+            SettingsField::AnthropicApiKey => mask_secret(&self.config.anthropic.api_key),
             SettingsField::AnthropicModel => self.config.anthropic.model.clone(),
-            SettingsField::Save | SettingsField::Cancel => String::new(),
+            SettingsField::Test | SettingsField::Save | SettingsField::Cancel => String::new(),
         }
     }
 }
 
-impl Widget for SettingsScreen<'_> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        // Get fields for current provider
-        let fields = SettingsField::fields_for_provider(self.config.provider.active);
+impl StatefulWidget for SettingsScreen<'_> {
+    type State = SettingsScreenState;
 
-        // Compute popup dimensions dynamically
-        let content_height: u16 = fields
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut SettingsScreenState) {
+        // Get fields for the provider being edited
+        let fields = SettingsField::fields_for_provider(self.tab);
+
+        // Size for the tallest tab so switching tabs doesn't resize the popup
+        let content_height: u16 = ProviderType::all()
             .iter()
-            .map(|f| if f.is_button() { 1u16 } else { 2u16 })
-            .sum();
-        // borders(2) + status bar(1) + padding(1)
-        let popup_height = (content_height + 4).min(area.height);
+            .map(|provider| {
+                SettingsField::fields_for_provider(*provider)
+                    .iter()
+                    .map(|f| if f.is_button() { 1u16 } else { 2u16 })
+                    .sum::<u16>()
+            })
+            .max()
+            .unwrap_or_default();
+        // tab bar(2) + borders(2) + status bar(1) + padding(1)
+        let popup_height = (content_height + 6).min(area.height);
         let popup_width = 60u16.min(area.width.saturating_sub(4));
 
         let popup_area = centered_rect(popup_width, popup_height, area);
@@ -131,22 +326,30 @@ impl Widget for SettingsScreen<'_> {
         let form_inner = form_block.inner(chunks[0]);
         form_block.render(chunks[0], buf);
 
-        // Calculate layout for fields
-        let field_constraints: Vec<Constraint> = fields
-            .iter()
-            .map(|f| {
-                if f.is_button() {
-                    Constraint::Length(1)
-                } else {
-                    Constraint::Length(2)
-                }
-            })
-            .collect();
+        // Calculate layout for fields, with the tab bar after the
+        // active-provider choice
+        let mut constraints = Vec::new();
+        for (idx, field) in fields.iter().enumerate() {
+            constraints.push(Constraint::Length(if field.is_button() { 1 } else { 2 }));
+            if idx == 0 {
+                constraints.push(Constraint::Length(2));
+            }
+        }
 
-        let field_chunks = Layout::default()
+        let form_chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(field_constraints)
+            .constraints(constraints)
             .split(form_inner);
+        let field_chunks: Vec<Rect> = form_chunks
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != 1)
+            .map(|(_, area)| *area)
+            .collect();
+
+        self.render_tabs(form_chunks[1], buf, state);
+        state.popup_area = popup_area;
+        state.field_areas = field_chunks.clone();
 
         for (idx, field) in fields.iter().enumerate() {
             let is_selected = idx == self.selected_field;
@@ -180,6 +383,7 @@ impl Widget for SettingsScreen<'_> {
                     Line::from(vec![
                         Span::styled(format!("{}: ", field.label()), label_style),
                         Span::styled(value, Style::default().fg(theme::PRIMARY)),
+                        Span::styled(self.override_hint(field), Style::default().fg(theme::WARNING)),
                         Span::styled(hint, Style::default().fg(theme::MUTED)),
                     ]),
                 ];
@@ -193,8 +397,8 @@ impl Widget for SettingsScreen<'_> {
                 };
 
                 let value = if self.editing && is_selected {
-                    if field.is_password() {
-                        "•".repeat(self.edit_value.len())
+                    if field.is_password() && !self.reveal {
+                        mask_secret_input(self.edit_value)
                     } else {
                         self.edit_value.to_string()
                     }
@@ -208,46 +412,111 @@ impl Widget for SettingsScreen<'_> {
                     Style::default().fg(theme::SECONDARY)
                 };
 
-                let cursor_hint = if is_selected && !self.editing {
-                    " (Enter to edit)"
+                let value_spans = if self.editing && is_selected {
+                    // The displayed value has one char per char typed, masked
+                    // or not, so the cursor's char index carries over
+                    let cursor = self.edit_value[..self.cursor_position.min(self.edit_value.len())]
+                        .chars()
+                        .count();
+                    let before: String = value.chars().take(cursor).collect();
+                    let at_cursor = value.chars().nth(cursor).map_or(" ".to_string(), String::from);
+                    let after: String = value.chars().skip(cursor + 1).collect();
+                    vec![
+                        Span::styled(before, value_style),
+                        Span::styled(
+                            at_cursor,
+                            Style::default().fg(theme::BACKGROUND).bg(theme::PRIMARY),
+                        ),
+                        Span::styled(after, value_style),
+                    ]
+                } else if value.is_empty() {
+                    vec![Span::styled("(empty)", Style::default().fg(theme::MUTED))]
                 } else {
+                    vec![Span::styled(value.clone(), value_style)]
+                };
+
+                let cursor_hint = if !is_selected || self.editing || self.picker.is_some() {
                     ""
+                } else if field.model_provider().is_some() {
+                    " (Enter to pick)"
+                } else {
+                    " (Enter to edit)"
                 };
 
+                // Check the value being typed, or the stored one
+                let error = field.config_key().and_then(|key| {
+                    let value = if self.editing && is_selected {
+                        self.edit_value.to_string()
+                    } else {
+                        get_config_value(self.config, key).ok()?
+                    };
+                    validate_config_value(key, &value).err()
+                });
+                let error_hint = error.map(|e| format!(" ✗ {}", e)).unwrap_or_default();
+
+                // Show where an API key comes from unless it's a plain config value
+                let source_hint = field
+                    .api_key_provider()
+                    .map(|provider| key_source(self.config, provider))
+                    .filter(|source| {
+                        !matches!(source, KeySource::Stored(SecretStorage::Plaintext) | KeySource::Missing)
+                    })
+                    .map(|source| format!(" [{}]", source.describe()))
+                    .unwrap_or_default();
+
                 let lines = vec![
                     Line::from(vec![
                         Span::styled(format!("{}: ", field.label()), label_style),
+                        Span::styled(error_hint, Style::default().fg(theme::ERROR)),
+                        Span::styled(self.override_hint(field), Style::default().fg(theme::WARNING)),
                     ]),
-                    Line::from(vec![
-                        Span::raw("  "),
-                        Span::styled(
-                            if value.is_empty() { "(empty)" } else { &value },
-                            if value.is_empty() {
-                                Style::default().fg(theme::MUTED)
-                            } else {
-                                value_style
-                            },
-                        ),
-                        Span::styled(cursor_hint, Style::default().fg(theme::MUTED)),
-                    ]),
+                    Line::from(
+                        [Span::raw("  ")]
+                            .into_iter()
+                            .chain(value_spans)
+                            .chain([
+                                Span::styled(source_hint, Style::default().fg(theme::MUTED)),
+                                Span::styled(cursor_hint, Style::default().fg(theme::MUTED)),
+                            ])
+                            .collect::<Vec<_>>(),
+                    ),
                 ];
 
                 Paragraph::new(Text::from(lines)).render(field_chunks[idx], buf);
             }
         }
 
+        if let (Some(picker), Some(&field_area)) = (self.picker, field_chunks.get(self.selected_field)) {
+            self.render_picker(picker, field_area, area, buf);
+        }
+
         // Status bar (below the form block)
         let status_text = if let Some(msg) = self.message {
             let color = if self.is_error { theme::ERROR } else { theme::SUCCESS };
             Line::styled(msg, Style::default().fg(color))
+        } else if self.picker.is_some() {
+            Line::styled(
+                "↑↓ Select | Enter to pick | F5 refresh | Esc to close",
+                Style::default().fg(theme::MUTED),
+            )
+        } else if self.editing && fields.get(self.selected_field).is_some_and(SettingsField::is_password) {
+            Line::styled(
+                "Enter to save | Ctrl+R to reveal | Esc to cancel",
+                Style::default().fg(theme::MUTED),
+            )
         } else if self.editing {
             Line::styled(
                 "Enter to save | Esc to cancel",
                 Style::default().fg(theme::MUTED),
             )
+        } else if fields.get(self.selected_field).is_some_and(SettingsField::is_provider_selector) {
+            Line::styled(
+                "←→ Change active provider | ↑↓ Navigate | Esc to close",
+                Style::default().fg(theme::MUTED),
+            )
         } else {
             Line::styled(
-                "↑↓ Navigate | Enter to edit | Tab to next | Esc to close",
+                "←→ Switch tab | ↑↓ Navigate | Enter to edit | Esc to close",
                 Style::default().fg(theme::MUTED),
             )
         };
@@ -53,6 +53,7 @@ impl Widget for CommandMenu {
         block.render(area, buf);
 
         let commands = self.filtered_commands();
+        let name_width = Command::all().iter().map(|cmd| cmd.name().len()).max().unwrap_or(0);
 
         let lines: Vec<Line> = commands
             .iter()
@@ -77,7 +78,7 @@ impl Widget for CommandMenu {
                 };
 
                 Line::from(vec![
-                    Span::styled(format!("{:<12}", cmd.name()), name_style),
+                    Span::styled(format!("{:<width$}", cmd.name(), width = name_width), name_style),
                     Span::styled(format!(" {}", cmd.description()), desc_style),
                 ])
             })
@@ -2,7 +2,7 @@ use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::Style,
-    text::{Line, Text},
+    text::{Line, Span, Text},
     widgets::{Paragraph, Widget},
 };
 
@@ -17,17 +17,36 @@ const LOGO: &str = r#"
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub struct Header {
+pub struct Header<'a> {
     show_logo: bool,
+    update_available: Option<&'a str>,
 }
 
-impl Header {
+impl<'a> Header<'a> {
     pub fn new(show_logo: bool) -> Self {
-        Self { show_logo }
+        Self {
+            show_logo,
+            update_available: None,
+        }
+    }
+
+    /// Newer release to mention next to the version, e.g. `v0.2.0`
+    pub fn update_available(mut self, version: Option<&'a str>) -> Self {
+        self.update_available = version;
+        self
+    }
+
+    fn update_notice(&self) -> Option<Span<'static>> {
+        self.update_available.map(|version| {
+            Span::styled(
+                format!("  {} available: run `mahoraga update`", version),
+                Style::default().fg(theme::MUTED),
+            )
+        })
     }
 }
 
-impl Widget for Header {
+impl Widget for Header<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         if self.show_logo {
             let logo_lines: Vec<Line> = LOGO
@@ -36,10 +55,11 @@ impl Widget for Header {
                 .map(|line| Line::styled(line, Style::default().fg(theme::PRIMARY)))
                 .collect();
 
-            let version_line = Line::styled(
+            let mut version_line = Line::styled(
                 format!(" v{}", VERSION),
                 Style::default().fg(theme::SECONDARY),
             );
+            version_line.extend(self.update_notice());
 
             let mut lines = logo_lines;
             lines.push(version_line);
@@ -49,17 +69,18 @@ impl Widget for Header {
             paragraph.render(area, buf);
         } else {
             // Compact header for smaller terminals
-            let line = Line::styled(
+            let mut line = Line::styled(
                 format!("MAHORAGA v{}", VERSION),
                 Style::default().fg(theme::PRIMARY),
             );
+            line.extend(self.update_notice());
             let paragraph = Paragraph::new(line);
             paragraph.render(area, buf);
         }
     }
 }
 
-impl Header {
+impl Header<'_> {
     /// Get the height of the header
     pub fn height(show_logo: bool) -> u16 {
         if show_logo {
@@ -27,6 +27,28 @@ impl<'a> PromptInput<'a> {
         self.is_focused = focused;
         self
     }
+
+    /// Area the prompt text is drawn in, inside the border and padding
+    pub fn text_area(area: Rect) -> Rect {
+        let inner_area = Block::default().borders(Borders::ALL).inner(area);
+        Rect {
+            x: inner_area.x + 1,
+            y: inner_area.y,
+            width: inner_area.width.saturating_sub(2),
+            height: inner_area.height,
+        }
+    }
+
+    /// First visible line, scrolled so the cursor line stays visible in tall prompts
+    pub fn scroll_offset(&self, height: u16) -> u16 {
+        if !self.is_focused {
+            return 0;
+        }
+        let cursor_line = self.content[..self.cursor_position.min(self.content.len())]
+            .matches('\n')
+            .count() as u16;
+        (cursor_line + 1).saturating_sub(height)
+    }
 }
 
 impl Widget for PromptInput<'_> {
@@ -41,16 +63,10 @@ impl Widget for PromptInput<'_> {
             .borders(Borders::ALL)
             .border_style(Style::default().fg(border_color));
 
-        let inner_area = block.inner(area);
         block.render(area, buf);
 
         // Add padding (1 char from left)
-        let padded_area = Rect {
-            x: inner_area.x + 1,
-            y: inner_area.y,
-            width: inner_area.width.saturating_sub(2),
-            height: inner_area.height,
-        };
+        let padded_area = Self::text_area(area);
 
         // Show placeholder with cursor when empty
         if self.content.is_empty() {
@@ -98,8 +114,8 @@ impl Widget for PromptInput<'_> {
                     let cursor_offset = self.cursor_position - line_start;
                     let before_cursor = &line_content[..cursor_offset.min(line_content.len())];
                     let at_cursor = line_content
-                        .chars()
-                        .nth(cursor_offset)
+                        .get(cursor_offset..)
+                        .and_then(|rest| rest.chars().next())
                         .map(|c| c.to_string())
                         .unwrap_or_else(|| " ".to_string());
                     let after_cursor = if cursor_offset < line_content.len() {
@@ -131,8 +147,9 @@ impl Widget for PromptInput<'_> {
                 char_count = line_end + 1;
             }
 
+            let scroll = self.scroll_offset(padded_area.height);
             let text = Text::from(rendered_lines);
-            let paragraph = Paragraph::new(text);
+            let paragraph = Paragraph::new(text).scroll((scroll, 0));
             paragraph.render(padded_area, buf);
         } else {
             // Split by newlines for proper multi-line display
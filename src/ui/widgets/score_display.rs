@@ -10,11 +10,18 @@ use crate::ui::theme::{self, score_color, score_label};
 
 pub struct ScoreDisplay {
     score: u8,
+    min_score: Option<u8>,
 }
 
 impl ScoreDisplay {
     pub fn new(score: u8) -> Self {
-        Self { score }
+        Self { score, min_score: None }
+    }
+
+    /// Flag scores below the project's minimum
+    pub fn min_score(mut self, min_score: Option<u8>) -> Self {
+        self.min_score = min_score;
+        self
     }
 }
 
@@ -43,14 +50,23 @@ impl Widget for ScoreDisplay {
         let color = score_color(self.score);
         let label = score_label(self.score);
 
-        let score_line = Line::from(vec![
+        let mut score_spans = vec![
             Span::styled(
                 format!("{}", self.score),
                 Style::default().fg(color),
             ),
             Span::styled("/100 - ", Style::default().fg(theme::MUTED)),
             Span::styled(label, Style::default().fg(color)),
-        ]);
+        ];
+        if let Some(min_score) = self.min_score {
+            let (text, color) = if self.score >= min_score {
+                (format!("  meets minimum {}", min_score), theme::SUCCESS)
+            } else {
+                (format!("  below minimum {}", min_score), theme::ERROR)
+            };
+            score_spans.push(Span::styled(text, Style::default().fg(color)));
+        }
+        let score_line = Line::from(score_spans);
 
         let score_paragraph = Paragraph::new(score_line);
         score_paragraph.render(layout[0], buf);